@@ -0,0 +1,141 @@
+use crate::settings::Settings;
+use crate::store::ModerationState;
+use chrono::{DateTime, Datelike, Utc};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+use std::io::{BufRead, BufReader, Write};
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditAction {
+    Approve,
+    // Approval that did not reach the quorum yet
+    Vote,
+    Unapprove,
+    Reject,
+    Delete,
+    Rotate,
+    Edit,
+    RemoveImage,
+}
+
+impl Display for AuditAction {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        // Reads as "<user> <action> review <id>"
+        let action = match self {
+            AuditAction::Approve => "approved",
+            AuditAction::Vote => "voted to approve",
+            AuditAction::Unapprove => "unapproved",
+            AuditAction::Reject => "rejected",
+            AuditAction::Delete => "deleted",
+            AuditAction::Rotate => "rotated an image of",
+            AuditAction::Edit => "edited",
+            AuditAction::RemoveImage => "removed an image from",
+        };
+        write!(f, "{}", action)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditEntry {
+    pub timestamp: DateTime<Utc>,
+    // Where the decision was made, missing for entries from before other sinks were recorded
+    #[serde(default = "default_sink")]
+    pub sink: String,
+    // Only known for discord users, the name is all we have of users of other sinks
+    pub user_id: Option<u64>,
+    pub user_name: String,
+    pub action: AuditAction,
+    pub review_id: String,
+    pub dish: Option<String>,
+    // Missing for entries from before it was recorded
+    #[serde(default)]
+    pub location: Option<String>,
+    // Only given when rejecting
+    #[serde(default)]
+    pub reason: Option<String>,
+    // Missing if the review is not in the store, e.g. because it was posted before the store existed
+    pub prior_state: Option<ModerationState>,
+}
+
+fn default_sink() -> String {
+    "discord".to_string()
+}
+
+/// Append-only record of every moderation decision, one JSON object per line.
+///
+/// Nothing is kept in memory, the file is only read when someone asks for the history of a review.
+pub struct AuditLog {
+    path: PathBuf,
+    // Keeps concurrent appends from interleaving
+    lock: Mutex<()>,
+}
+
+impl AuditLog {
+    pub fn open(settings: &Settings) -> Self {
+        let path = PathBuf::from(&settings.audit.path);
+        info!("Writing audit log to {}", path.display());
+
+        Self {
+            path,
+            lock: Mutex::new(()),
+        }
+    }
+
+    pub fn append(&self, entry: &AuditEntry) -> anyhow::Result<()> {
+        let mut line = serde_json::to_string(entry)?;
+        line.push('\n');
+
+        let _guard = self.lock.lock().unwrap();
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        file.write_all(line.as_bytes())?;
+        Ok(())
+    }
+
+    /// All entries for the review, oldest first
+    pub fn history(&self, review_id: &str) -> anyhow::Result<Vec<AuditEntry>> {
+        self.read(|entry| entry.review_id == review_id)
+    }
+
+    /// All rejections within the month, oldest first
+    pub fn rejections(&self, year: i32, month: u32) -> anyhow::Result<Vec<AuditEntry>> {
+        self.read(|entry| {
+            entry.action == AuditAction::Reject
+                && entry.timestamp.year() == year
+                && entry.timestamp.month() == month
+        })
+    }
+
+    fn read(&self, filter: impl Fn(&AuditEntry) -> bool) -> anyhow::Result<Vec<AuditEntry>> {
+        let _guard = self.lock.lock().unwrap();
+        if !self.path.exists() {
+            return Ok(vec![]);
+        }
+
+        let file = std::fs::File::open(&self.path)?;
+        let mut entries = vec![];
+        for (nr, line) in BufReader::new(file).lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            match serde_json::from_str::<AuditEntry>(&line) {
+                Ok(entry) if filter(&entry) => entries.push(entry),
+                Ok(_) => {}
+                Err(err) => {
+                    // A crash mid-write leaves a partial line, which shouldn't hide the rest
+                    warn!("Skipping invalid audit log line {}: {}", nr + 1, err);
+                }
+            }
+        }
+
+        Ok(entries)
+    }
+}
@@ -1,23 +1,51 @@
-use crate::gql::client::MensattGqlClient;
+use crate::audit::{AuditAction, AuditEntry, AuditLog};
+use crate::discord::custom_id::{ComponentAction, CustomId};
+use crate::events::{EventBus, ReviewEvent};
+use crate::gql::client::{GqlError, MensattGqlClient, Operation};
 use crate::gql::{Review, Uuid};
 use crate::image::ImageClient;
-use crate::settings::Settings;
+use crate::settings::{Action, Settings, OTHER_REASON};
+use crate::sinks::render::shorten;
+use crate::sinks::{NotificationSink, ReviewUpdate};
+use crate::store::{Claim, ClaimOutcome, ModerationState, ReviewRecord, ReviewStore, Vote};
+use anyhow::Context as _;
+use chrono::{Datelike, NaiveDate, TimeDelta, Utc};
 use log::{debug, info, warn};
 use serenity::all::{
-    ActionRowComponent, ButtonStyle, Colour, Context, CreateButton, CreateCommand, CreateEmbed,
-    CreateEmbedAuthor, CreateInteractionResponse, CreateInteractionResponseFollowup,
-    CreateInteractionResponseMessage, CreateMessage, CreateModal, EditMessage, EventHandler,
-    GatewayIntents, GuildId, Http, InputTextStyle, Interaction, ReactionType, Ready, Timestamp,
+    ActionRow, ActionRowComponent, Attachment, Button, ButtonKind, ButtonStyle, Colour,
+    CommandOptionType, ComponentInteraction, ComponentInteractionDataKind, Context, CreateButton,
+    CreateCommand, CreateCommandOption, CreateEmbed, CreateEmbedAuthor, CreateInteractionResponse,
+    CreateInteractionResponseFollowup, CreateInteractionResponseMessage, CreateMessage,
+    CreateModal, CreateSelectMenu, CreateSelectMenuKind, CreateSelectMenuOption,
+    EditInteractionResponse, EditMessage, Embed, EventHandler, GatewayIntents, GuildId, Http,
+    InputTextStyle, Interaction, Member, Message, MessageFlags, MessageId, Permissions,
+    ReactionType, Ready, SelectMenu, Timestamp, User,
 };
-use serenity::builder::{CreateActionRow, CreateInputText};
+use serenity::builder::{CreateActionRow, CreateAttachment, CreateInputText, EditAttachments};
 use serenity::model::id::ChannelId;
 use serenity::prelude::TypeMapKey;
 use serenity::{async_trait, Client};
+use std::collections::{BTreeMap, HashMap};
 use std::str::FromStr;
 use std::sync::Arc;
 
 struct Handler;
 
+const EDIT_DISPLAY_NAME_FIELD: &str = "display_name_field";
+const EDIT_TEXT_FIELD: &str = "text_field";
+const EDIT_STARS_FIELD: &str = "stars_field";
+const REJECT_REASON_FIELD: &str = "reason_field";
+
+// Discord allows up to 25 options per menu, one of which is used for other reasons
+const MAX_REJECT_REASONS: usize = 24;
+// Discord cuts off button labels after 80 characters
+const MAX_LABEL_LEN: usize = 80;
+// Errors are shortened to this many characters when shown to moderators
+const MAX_ERROR_LEN: usize = 300;
+
+// Discord allows up to 10 embeds per message, each of them shows one image
+const MAX_EMBEDS: usize = 10;
+
 impl TypeMapKey for MensattGqlClient {
     type Value = Arc<MensattGqlClient>;
 }
@@ -30,7 +58,19 @@ impl TypeMapKey for Settings {
     type Value = Arc<Settings>;
 }
 
-#[derive(Eq, PartialEq)]
+impl TypeMapKey for ReviewStore {
+    type Value = Arc<ReviewStore>;
+}
+
+impl TypeMapKey for AuditLog {
+    type Value = Arc<AuditLog>;
+}
+
+impl TypeMapKey for EventBus {
+    type Value = EventBus;
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
 enum ReviewMessageState {
     New,
     Approve,
@@ -39,6 +79,100 @@ enum ReviewMessageState {
     Delete,
 }
 
+impl ReviewMessageState {
+    fn moderation_state(&self) -> ModerationState {
+        match self {
+            ReviewMessageState::New => ModerationState::Pending,
+            ReviewMessageState::Approve => ModerationState::Approved,
+            ReviewMessageState::Unapprove => ModerationState::Unapproved,
+            ReviewMessageState::Reject => ModerationState::Rejected,
+            ReviewMessageState::Delete => ModerationState::Deleted,
+        }
+    }
+
+    /// The state after the action, `None` if the action is not possible in this state
+    fn apply(&self, action: &ComponentAction) -> Option<ReviewMessageState> {
+        use ReviewMessageState::*;
+
+        match (self, action) {
+            // Nothing can be done with a review that is gone
+            (Delete, _) => None,
+            (New | Unapprove | Reject, ComponentAction::Approve) => Some(Approve),
+            (Approve, ComponentAction::Unapprove) => Some(Unapprove),
+            // Rejecting only asks for a reason, which then rejects the review
+            (New | Unapprove, ComponentAction::Reject) => Some(*self),
+            (New | Unapprove, ComponentAction::Reason) => Some(Reject),
+            // Only rejected reviews show the delete button
+            (Reject, ComponentAction::Delete) => Some(Delete),
+            (
+                _,
+                ComponentAction::Approve
+                | ComponentAction::Unapprove
+                | ComponentAction::Reject
+                | ComponentAction::Reason
+                | ComponentAction::Delete,
+            ) => None,
+            // Everything else leaves the state as is
+            (
+                _,
+                ComponentAction::Edit
+                | ComponentAction::Claim
+                | ComponentAction::ChooseImage
+                | ComponentAction::Rotate { .. }
+                | ComponentAction::Remove { .. }
+                | ComponentAction::ConfirmRemove { .. }
+                | ComponentAction::CancelRemove,
+            ) => Some(*self),
+        }
+    }
+
+    fn describe(&self) -> &'static str {
+        match self {
+            ReviewMessageState::New => "new",
+            ReviewMessageState::Approve => "approved",
+            ReviewMessageState::Unapprove => "unapproved",
+            ReviewMessageState::Reject => "rejected",
+            ReviewMessageState::Delete => "deleted",
+        }
+    }
+}
+
+impl From<ModerationState> for ReviewMessageState {
+    fn from(state: ModerationState) -> Self {
+        match state {
+            ModerationState::Pending => ReviewMessageState::New,
+            ModerationState::Approved => ReviewMessageState::Approve,
+            ModerationState::Unapproved => ReviewMessageState::Unapprove,
+            ModerationState::Rejected => ReviewMessageState::Reject,
+            ModerationState::Deleted => ReviewMessageState::Delete,
+        }
+    }
+}
+
+/// Approvals a review has and needs
+#[derive(Clone, Copy, Default)]
+struct Quorum {
+    votes: usize,
+    required: usize,
+}
+
+impl Quorum {
+    fn label(&self) -> String {
+        if self.required > 1 {
+            format!("Approve {}/{}", self.votes, self.required)
+        } else {
+            "Approve".to_string()
+        }
+    }
+}
+
+/// The user-editable parts of a review, as currently shown in its message
+struct ReviewFields {
+    display_name: Option<String>,
+    text: Option<String>,
+    stars: i32,
+}
+
 #[async_trait]
 impl EventHandler for Handler {
     async fn ready(&self, ctx: Context, data_about_bot: Ready) {
@@ -49,8 +183,25 @@ impl EventHandler for Handler {
         debug!("{:#?}", data_about_bot);
 
         info!("Registering slash commands");
-        let recover =
-            CreateCommand::new("recover").description("Sends messages for all unapproved reviews");
+        // Only the default, server admins can still allow it for other roles or members
+        let recover = CreateCommand::new("recover")
+            .description("Sends messages for all unapproved reviews")
+            .default_member_permissions(Permissions::MANAGE_MESSAGES);
+        let report = CreateCommand::new("report")
+            .description("Counts the rejection reasons per location")
+            .add_option(CreateCommandOption::new(
+                CommandOptionType::String,
+                "month",
+                "Month as YYYY-MM, defaults to the current one",
+            ))
+            .default_member_permissions(Permissions::MANAGE_MESSAGES);
+        let audit = CreateCommand::new("audit")
+            .description("Shows who moderated a review")
+            .add_option(
+                CreateCommandOption::new(CommandOptionType::String, "review", "Id of the review")
+                    .required(true),
+            )
+            .default_member_permissions(Permissions::MANAGE_MESSAGES);
 
         {
             let guard = ctx.data.read().await;
@@ -61,12 +212,14 @@ impl EventHandler for Handler {
                 let guild = GuildId::new(*gid);
                 info!("Registering commands for {}", gid);
 
-                match guild.create_command(&ctx.http, recover.clone()).await {
-                    Ok(_) => {}
-                    Err(e) => {
-                        warn!("Error registering slash command: {:?}", e);
-                    }
-                };
+                for command in [&recover, &audit, &report] {
+                    match guild.create_command(&ctx.http, command.clone()).await {
+                        Ok(_) => {}
+                        Err(e) => {
+                            warn!("Error registering slash command: {:?}", e);
+                        }
+                    };
+                }
             }
         }
     }
@@ -77,7 +230,30 @@ impl EventHandler for Handler {
                 info!("Received command interaction: {:#?}", cmd);
                 match cmd.data.name.as_str() {
                     "recover" => {
-                        let (reviews, settings) = {
+                        if !is_permitted(&ctx, Action::Recover, cmd.member.as_deref()).await {
+                            info!("Refused recover command of {}", cmd.user.name);
+                            match cmd
+                                .create_response(ctx.http.clone(), get_refusal(Action::Recover))
+                                .await
+                            {
+                                Ok(_) => {}
+                                Err(err) => {
+                                    warn!("Failed to create response: {}", err);
+                                }
+                            }
+                            return;
+                        }
+
+                        // Checking every known message takes longer than discord waits for an answer
+                        match cmd.defer(ctx.http.clone()).await {
+                            Ok(_) => {}
+                            Err(err) => {
+                                warn!("Failed to defer slash command interaction: {}", err);
+                                return;
+                            }
+                        }
+
+                        let (reviews, settings, image_client, store) = {
                             let guard = ctx.data.read().await;
                             let gql_client = guard
                                 .get::<MensattGqlClient>()
@@ -85,51 +261,196 @@ impl EventHandler for Handler {
                             let settings = guard
                                 .get::<Settings>()
                                 .expect("Could not retrieve settings from global context");
-                            (gql_client.get_unapproved_reviews().await, settings.clone())
+                            let image_client = guard
+                                .get::<ImageClient>()
+                                .expect("Could not retrieve ImageClient from global context");
+                            let store = guard
+                                .get::<ReviewStore>()
+                                .expect("Could not retrieve ReviewStore from global context");
+                            (
+                                gql_client.get_unapproved_reviews().await,
+                                settings.clone(),
+                                image_client.clone(),
+                                store.clone(),
+                            )
                         };
-                        match reviews {
-                            Ok(reviews) => {
-                                let comms = ChannelId::new(settings.discord.comm_channel);
-                                let nr = reviews.len();
+                        let reviews = match reviews {
+                            Ok(reviews) => reviews,
+                            Err(err) => {
+                                warn!("Error getting unapproved reviews: {:?}", err);
+                                let content = format!(
+                                    "Could not get the unapproved reviews: {}",
+                                    describe_error(&err)
+                                );
                                 match cmd
-                                    .create_response(
+                                    .edit_response(
                                         ctx.http.clone(),
-                                        CreateInteractionResponse::Message(
-                                            CreateInteractionResponseMessage::new().content(
-                                                format!("Recovering {} reviews for you ^-^", nr),
-                                            ),
-                                        ),
+                                        EditInteractionResponse::new().content(content),
                                     )
                                     .await
                                 {
                                     Ok(_) => {}
                                     Err(err) => {
-                                        warn!(
-                                            "Could not reply to slash command interaction: {:#?}",
-                                            err
-                                        );
+                                        warn!("Failed to edit response: {}", err);
                                     }
                                 }
-                                for r in reviews {
-                                    match comms
-                                        .send_message(
-                                            ctx.http.clone(),
-                                            create_review_embed(&settings, r),
-                                        )
-                                        .await
-                                    {
-                                        Ok(_) => {}
-                                        Err(err) => {
-                                            warn!(
-                                                "Could not send recovered review message: {:#?}",
-                                                err
-                                            );
-                                        }
+                                return;
+                            }
+                        };
+
+                        // Reviews that still have a message only get linked, not reposted
+                        let mut existing = vec![];
+                        let mut missing = vec![];
+                        let mut unchecked = 0;
+                        for r in reviews {
+                            let record = match store.get(&r.id.0) {
+                                Some(record) => record,
+                                None => {
+                                    missing.push(r);
+                                    continue;
+                                }
+                            };
+                            let channel = ChannelId::new(record.channel_id);
+                            match channel.message(&ctx.http, record.message_id).await {
+                                Ok(_) => existing.push((
+                                    r.occurrence.dish.name_de,
+                                    MessageId::new(record.message_id).link(channel, cmd.guild_id),
+                                )),
+                                Err(err) if is_unknown_message(&err) => {
+                                    info!("Message for review {} is gone, posting it again", r.id);
+                                    missing.push(r);
+                                }
+                                // The message may well still exist, posting it again would duplicate it
+                                Err(err) => {
+                                    warn!("Could not check message for review {}: {}", r.id, err);
+                                    unchecked += 1;
+                                }
+                            }
+                        }
+
+                        match cmd
+                            .edit_response(
+                                ctx.http.clone(),
+                                EditInteractionResponse::new().content(get_recover_message(
+                                    missing.len(),
+                                    &existing,
+                                    unchecked,
+                                )),
+                            )
+                            .await
+                        {
+                            Ok(_) => {}
+                            Err(err) => {
+                                warn!("Could not reply to slash command interaction: {:#?}", err);
+                            }
+                        }
+                        for r in missing {
+                            match post_review(&ctx.http, &settings, &image_client, &store, r).await
+                            {
+                                Ok(_) => {}
+                                Err(err) => {
+                                    warn!("Could not send recovered review message: {:#?}", err);
+                                }
+                            }
+                        }
+                    }
+                    "audit" => {
+                        let review_id = cmd
+                            .data
+                            .options
+                            .iter()
+                            .find(|option| option.name == "review")
+                            .and_then(|option| option.value.as_str())
+                            .unwrap_or_default()
+                            .trim()
+                            .to_string();
+
+                        let history = {
+                            let guard = ctx.data.read().await;
+                            let audit_log = guard
+                                .get::<AuditLog>()
+                                .expect("Could not retrieve AuditLog from global context");
+                            audit_log.history(&review_id)
+                        };
+
+                        let content = match history {
+                            Ok(history) => get_audit_message(&review_id, &history),
+                            Err(err) => {
+                                warn!("Could not read audit log: {}", err);
+                                format!("Could not read the audit log: {}", err)
+                            }
+                        };
+
+                        match cmd
+                            .create_response(
+                                ctx.http.clone(),
+                                CreateInteractionResponse::Message(
+                                    CreateInteractionResponseMessage::new()
+                                        .ephemeral(true)
+                                        .content(content),
+                                ),
+                            )
+                            .await
+                        {
+                            Ok(_) => {}
+                            Err(err) => {
+                                warn!("Failed to create response: {}", err);
+                            }
+                        }
+                    }
+                    "report" => {
+                        let month = cmd
+                            .data
+                            .options
+                            .iter()
+                            .find(|option| option.name == "month")
+                            .and_then(|option| option.value.as_str())
+                            .map(|month| month.trim().to_string());
+
+                        let date = match month {
+                            Some(month) => {
+                                NaiveDate::parse_from_str(&format!("{}-01", month), "%Y-%m-%d").ok()
+                            }
+                            None => Some(Utc::now().date_naive()),
+                        };
+
+                        let content = match date {
+                            Some(date) => {
+                                let rejections = {
+                                    let guard = ctx.data.read().await;
+                                    let audit_log = guard
+                                        .get::<AuditLog>()
+                                        .expect("Could not retrieve AuditLog from global context");
+                                    audit_log.rejections(date.year(), date.month())
+                                };
+
+                                match rejections {
+                                    Ok(rejections) => {
+                                        get_report_message(date.year(), date.month(), &rejections)
+                                    }
+                                    Err(err) => {
+                                        warn!("Could not read audit log: {}", err);
+                                        format!("Could not read the audit log: {}", err)
                                     }
                                 }
                             }
+                            None => "The month must look like 2024-01".to_string(),
+                        };
+
+                        match cmd
+                            .create_response(
+                                ctx.http.clone(),
+                                CreateInteractionResponse::Message(
+                                    CreateInteractionResponseMessage::new()
+                                        .ephemeral(true)
+                                        .content(content),
+                                ),
+                            )
+                            .await
+                        {
+                            Ok(_) => {}
                             Err(err) => {
-                                warn!("Error getting unapproved reviews: {:?}", err);
+                                warn!("Failed to create response: {}", err);
                             }
                         }
                     }
@@ -141,56 +462,256 @@ impl EventHandler for Handler {
             Interaction::Component(mut cmp) => {
                 info!("Received component interaction: {:#?}", cmp);
 
-                let split = cmp.data.custom_id.split("_").collect::<Vec<_>>();
-                if split.len() < 2 || split.len() > 3 {
-                    warn!(
-                        "Received component interaction with invalid custom id: {}",
-                        cmp.data.custom_id
-                    );
-                    return;
+                let custom_id = match cmp.data.custom_id.parse::<CustomId>() {
+                    Ok(custom_id) => custom_id,
+                    Err(err) => {
+                        warn!(
+                            "Received component interaction with invalid custom id {}: {}",
+                            cmp.data.custom_id, err
+                        );
+                        match cmp
+                            .create_response(
+                                ctx.http.clone(),
+                                CreateInteractionResponse::Message(
+                                    CreateInteractionResponseMessage::new()
+                                        .ephemeral(true)
+                                        .content(
+                                            "This button is outdated and can't be used anymore",
+                                        ),
+                                ),
+                            )
+                            .await
+                        {
+                            Ok(_) => {}
+                            Err(err) => {
+                                warn!("Failed to create response: {}", err);
+                            }
+                        }
+                        return;
+                    }
+                };
+                let review_id = custom_id.review_id.as_str();
+                let current = get_review_state(&ctx, review_id)
+                    .await
+                    .map(ReviewMessageState::from);
+
+                // Old messages use the same id for rejecting and unapproving
+                let action = match custom_id.action {
+                    ComponentAction::Reject
+                        if custom_id.legacy && current == Some(ReviewMessageState::Approve) =>
+                    {
+                        ComponentAction::Unapprove
+                    }
+                    // Old messages show their only image in the embed, so it isn't part of the id
+                    ComponentAction::Rotate { image_id, angle } if image_id.is_empty() => {
+                        ComponentAction::Rotate {
+                            image_id: get_embed_image_id(&cmp.message).unwrap_or_default(),
+                            angle,
+                        }
+                    }
+                    action => action,
+                };
+
+                // Retries come from the error message and the controls of an image from their own
+                // message, but both have to act on the review's message
+                let is_image_control = matches!(
+                    action,
+                    ComponentAction::Rotate { .. } | ComponentAction::Remove { .. }
+                ) && cmp
+                    .message
+                    .flags
+                    .is_some_and(|flags| flags.contains(MessageFlags::EPHEMERAL));
+                if custom_id.retry || is_image_control {
+                    match get_review_message(&ctx, review_id).await {
+                        Ok(message) => *cmp.message = message,
+                        Err(err) => {
+                            warn!("Could not load message of review {}: {:#}", review_id, err);
+                            match cmp
+                                .create_response(
+                                    ctx.http.clone(),
+                                    CreateInteractionResponse::Message(
+                                        CreateInteractionResponseMessage::new()
+                                            .ephemeral(true)
+                                            .content(format!(
+                                                "The review's message could not be found: {}",
+                                                describe_error(&err)
+                                            )),
+                                    ),
+                                )
+                                .await
+                            {
+                                Ok(_) => {}
+                                Err(err) => {
+                                    warn!("Failed to create response: {}", err);
+                                }
+                            }
+                            return;
+                        }
+                    }
+                }
+                let retry = CustomId::new(review_id, action.clone()).for_retry();
+
+                if let Some(permission) = get_action(&action) {
+                    if !is_permitted(&ctx, permission, cmp.member.as_ref()).await {
+                        info!(
+                            "Refused {:?} interaction of {} on review {}",
+                            action, cmp.user.name, review_id
+                        );
+                        match cmp
+                            .create_response(ctx.http.clone(), get_refusal(permission))
+                            .await
+                        {
+                            Ok(_) => {}
+                            Err(err) => {
+                                warn!("Failed to create response: {}", err);
+                            }
+                        }
+                        return;
+                    }
+                }
+
+                // Without a record, e.g. for messages from before the store existed, we can't tell
+                if let Some(current) = &current {
+                    if current.apply(&action).is_none() {
+                        info!(
+                            "Refused {:?} interaction on review {}, which is {}",
+                            action,
+                            review_id,
+                            current.describe()
+                        );
+                        match cmp
+                            .create_response(
+                                ctx.http.clone(),
+                                CreateInteractionResponse::Message(
+                                    CreateInteractionResponseMessage::new()
+                                        .ephemeral(true)
+                                        .content(format!(
+                                            "This can't be done anymore, the review is {} now",
+                                            current.describe()
+                                        )),
+                                ),
+                            )
+                            .await
+                        {
+                            Ok(_) => {}
+                            Err(err) => {
+                                warn!("Failed to create response: {}", err);
+                            }
+                        }
+                        return;
+                    }
                 }
 
-                let review_id = split[1];
+                // Someone else handling the review doesn't prevent voting, only the final approval
+                if !matches!(
+                    action,
+                    ComponentAction::Claim | ComponentAction::CancelRemove
+                ) {
+                    if let Some(claim) = get_foreign_claim(&ctx, review_id, cmp.user.id.get()).await
+                    {
+                        let is_vote = action == ComponentAction::Approve && {
+                            let quorum = get_quorum(&ctx, review_id, &cmp.message).await;
+                            quorum.votes + 1 < quorum.required
+                        };
+
+                        if !is_vote {
+                            info!(
+                                "Refused {:?} interaction of {} on review {} claimed by {}",
+                                action, cmp.user.name, review_id, claim.user_name
+                            );
+                            match cmp
+                                .create_response(
+                                    ctx.http.clone(),
+                                    CreateInteractionResponse::Message(
+                                        CreateInteractionResponseMessage::new()
+                                            .ephemeral(true)
+                                            .content(get_claim_message(&claim)),
+                                    ),
+                                )
+                                .await
+                            {
+                                Ok(_) => {}
+                                Err(err) => {
+                                    warn!("Failed to create response: {}", err);
+                                }
+                            }
+                            return;
+                        }
+                    }
+                }
 
                 // We are gonna take a while, let's tell discord to calm down a bit
                 // TODO: Don't think this is necessary, as we take less than 5s?
-                match cmp.defer(ctx.http.clone()).await {
-                    Ok(_) => {}
-                    Err(e) => {
-                        warn!("Failed to defer message: {}", e);
-                        warn!("Message: {:#?}", cmp.message);
-                        return;
+                // Editing responds with a modal, choosing an image with its controls, removing an
+                // image with a confirmation and rejecting with a menu of reasons, none of which is
+                // possible after deferring
+                if !matches!(
+                    action,
+                    ComponentAction::Reject
+                        | ComponentAction::Reason
+                        | ComponentAction::Edit
+                        | ComponentAction::ChooseImage
+                        | ComponentAction::Remove { .. }
+                        | ComponentAction::ConfirmRemove { .. }
+                        | ComponentAction::CancelRemove
+                ) {
+                    match cmp.defer(ctx.http.clone()).await {
+                        Ok(_) => {}
+                        Err(e) => {
+                            warn!("Failed to defer message: {}", e);
+                            warn!("Message: {:#?}", cmp.message);
+                            return;
+                        }
                     }
                 }
 
-                match split[0] {
-                    "approve" | "reject" => {
-                        let state = if split[0] == "approve" {
+                match &action {
+                    ComponentAction::Reject => {
+                        let reasons = {
+                            let guard = ctx.data.read().await;
+                            let settings = guard
+                                .get::<Settings>()
+                                .expect("Could not retrieve settings from global context");
+                            settings.discord.reject_reasons.clone()
+                        };
+
+                        match cmp
+                            .create_response(
+                                ctx.http.clone(),
+                                CreateInteractionResponse::Message(get_reason_menu(
+                                    review_id, &reasons,
+                                )),
+                            )
+                            .await
+                        {
+                            Ok(_) => {}
+                            Err(err) => {
+                                warn!("Failed to create response: {}", err);
+                                warn!("Message: {:#?}", cmp.message);
+                            }
+                        }
+                    }
+                    ComponentAction::Approve | ComponentAction::Unapprove => {
+                        let state = if action == ComponentAction::Approve {
                             ReviewMessageState::Approve
                         } else {
-                            // Hack:If the first button (e.g. the approve button) was disabled,
-                            // then the review was approved and we want the typical reject button
-                            // back.
-                            // Unwraps should be safe, as the number of components is constant,
-                            // same as the first() calls. Note that this may break if the
-                            // button layout changes.
-                            let was_approved = cmp
-                                .message
-                                .components
-                                .first()
-                                .unwrap()
-                                .components
-                                .first()
-                                .unwrap();
-                            if let ActionRowComponent::Button(button) = was_approved {
-                                if button.disabled {
-                                    ReviewMessageState::Unapprove
-                                } else {
-                                    ReviewMessageState::Reject
-                                }
-                            } else {
-                                ReviewMessageState::Reject
+                            ReviewMessageState::Unapprove
+                        };
+
+                        if let Err(err) = check_backend_state(&ctx, review_id).await {
+                            info!("Not moderating review {}: {:#}", review_id, err);
+                            report_failure(&ctx, &cmp, "Nothing was changed", &err, Some(&retry))
+                                .await;
+                            return;
+                        }
+
+                        let previous = if state == ReviewMessageState::Approve {
+                            match vote(&ctx, &cmp, review_id).await {
+                                Some(previous) => previous,
+                                None => return,
                             }
+                        } else {
+                            set_review_state(&ctx, review_id, state.moderation_state()).await
                         };
 
                         // Scope to minimize the time the lock is held
@@ -202,23 +723,69 @@ impl EventHandler for Handler {
                                 .expect("Could not retrieve MensattGqlClient from global context");
 
                             match gql_client
-                                .update_review(Uuid(review_id.to_string()), split[0] == "approve")
+                                .update_review(
+                                    Uuid(review_id.to_string()),
+                                    state == ReviewMessageState::Approve,
+                                )
                                 .await
                             {
                                 Ok(_) => {}
                                 Err(err) => {
                                     warn!("Failed to update review: {}", err);
                                     warn!("Original message: {:#?}", cmp.message);
+                                    if let Some(previous) = previous {
+                                        set_review_state(&ctx, review_id, previous).await;
+                                    }
+                                    // Otherwise the user could neither vote again nor approve
+                                    remove_vote(&ctx, review_id, cmp.user.id.get()).await;
+                                    report_failure(
+                                        &ctx,
+                                        &cmp,
+                                        "Could not update the review",
+                                        &err,
+                                        Some(&retry),
+                                    )
+                                    .await;
                                     return;
                                 }
                             };
                         }
 
+                        let action = if state == ReviewMessageState::Approve {
+                            AuditAction::Approve
+                        } else {
+                            AuditAction::Unapprove
+                        };
+                        audit(
+                            &ctx,
+                            &cmp.user,
+                            action,
+                            review_id,
+                            get_dish(&cmp.message),
+                            previous,
+                            None,
+                        )
+                        .await;
+
+                        // Approvals are reported by the backend itself
+                        if state == ReviewMessageState::Unapprove {
+                            publish_event(
+                                &ctx,
+                                ReviewEvent::Unapproved(Uuid(review_id.to_string())),
+                            )
+                            .await;
+                        } else {
+                            forget_review(&ctx, review_id).await;
+                        }
+
+                        let quorum = get_quorum(&ctx, review_id, &cmp.message).await;
                         let msg_edit = EditMessage::new().components(get_action_row(
-                            state,
+                            &state,
                             review_id,
-                            cmp.message.embeds.first().unwrap().image.is_some(),
-                            cmp.user.name.as_str(),
+                            &get_image_ids(&cmp.message.attachments),
+                            Some(cmp.user.name.as_str()),
+                            None,
+                            quorum,
                         ));
 
                         match cmp.message.edit(ctx.http.clone(), msg_edit).await {
@@ -226,12 +793,179 @@ impl EventHandler for Handler {
                             Err(e) => {
                                 warn!("Failed to edit message: {}", e);
                                 warn!("Message: {:#?}", cmp.message);
+                                report_failure(
+                                    &ctx,
+                                    &cmp,
+                                    &format!(
+                                        "The review was {}, but its message could not be updated",
+                                        state.describe()
+                                    ),
+                                    &e.into(),
+                                    None,
+                                )
+                                .await;
                                 return;
                             }
                         };
                     }
-                    "delete" => {
-                        {
+                    ComponentAction::Reason => {
+                        let reason = match &cmp.data.kind {
+                            ComponentInteractionDataKind::StringSelect { values } => {
+                                values.first().cloned()
+                            }
+                            _ => None,
+                        };
+                        let reason = match reason {
+                            Some(reason) => reason,
+                            None => {
+                                warn!("Received reason interaction without a selected reason");
+                                warn!("Interaction: {:#?}", cmp);
+                                return;
+                            }
+                        };
+
+                        // The reason has to be typed in, which needs a modal
+                        if reason == OTHER_REASON {
+                            match cmp
+                                .create_response(
+                                    ctx.http.clone(),
+                                    CreateInteractionResponse::Modal(get_reason_modal(review_id)),
+                                )
+                                .await
+                            {
+                                Ok(_) => {}
+                                Err(err) => {
+                                    warn!("Failed to create response: {}", err);
+                                }
+                            }
+                            return;
+                        }
+
+                        // The menu is the message of this interaction, not the review
+                        match cmp
+                            .create_response(
+                                ctx.http.clone(),
+                                CreateInteractionResponse::UpdateMessage(
+                                    CreateInteractionResponseMessage::new()
+                                        .content("Rejecting review...")
+                                        .components(vec![]),
+                                ),
+                            )
+                            .await
+                        {
+                            Ok(_) => {}
+                            Err(err) => {
+                                warn!("Failed to create response: {}", err);
+                                return;
+                            }
+                        }
+
+                        let response = get_rejection_result(
+                            review_id,
+                            &reason,
+                            reject_review(&ctx, &cmp.user, review_id, &reason).await,
+                        );
+                        match cmp.edit_response(ctx.http.clone(), response).await {
+                            Ok(_) => {}
+                            Err(err) => {
+                                warn!("Failed to edit response: {}", err);
+                            }
+                        }
+                    }
+                    ComponentAction::Claim => {
+                        let (store, timeout) = {
+                            let guard = ctx.data.read().await;
+                            let settings = guard
+                                .get::<Settings>()
+                                .expect("Could not retrieve settings from global context");
+                            let store = guard
+                                .get::<ReviewStore>()
+                                .expect("Could not retrieve ReviewStore from global context");
+                            (store.clone(), settings.discord.claim_timeout_secs)
+                        };
+
+                        let until = Utc::now() + TimeDelta::seconds(timeout as i64);
+                        let claim = Claim {
+                            user_id: cmp.user.id.get(),
+                            user_name: cmp.user.name.clone(),
+                            until,
+                        };
+                        let components = match store.toggle_claim(review_id, claim).await {
+                            Ok(ClaimOutcome::Claimed) => {
+                                // Nothing resets the button once the claim expires, so it tells when
+                                let label = format!(
+                                    "Claimed by {} until {} UTC",
+                                    cmp.user.name,
+                                    until.format("%H:%M")
+                                );
+                                edit_button(
+                                    &cmp.message.components,
+                                    ComponentAction::Claim,
+                                    |button| {
+                                        button.label(label.clone()).style(ButtonStyle::Primary)
+                                    },
+                                )
+                            }
+                            Ok(ClaimOutcome::Released) => edit_button(
+                                &cmp.message.components,
+                                ComponentAction::Claim,
+                                |button| button.label("Claim").style(ButtonStyle::Secondary),
+                            ),
+                            Ok(ClaimOutcome::Taken(claim)) => {
+                                send_ephemeral_followup(&ctx, &cmp, &get_claim_message(&claim))
+                                    .await;
+                                return;
+                            }
+                            Ok(ClaimOutcome::UnknownReview) => {
+                                send_ephemeral_followup(
+                                    &ctx,
+                                    &cmp,
+                                    "This review can't be claimed, as it was sent before claims \
+                                     existed",
+                                )
+                                .await;
+                                return;
+                            }
+                            Err(err) => {
+                                warn!("Could not claim review {}: {}", review_id, err);
+                                send_ephemeral_followup(&ctx, &cmp, "Could not claim the review")
+                                    .await;
+                                return;
+                            }
+                        };
+
+                        match cmp
+                            .message
+                            .edit(ctx.http.clone(), EditMessage::new().components(components))
+                            .await
+                        {
+                            Ok(_) => {}
+                            Err(e) => {
+                                warn!("Failed to edit message: {}", e);
+                                warn!("Message: {:#?}", cmp.message);
+                                report_failure(
+                                    &ctx,
+                                    &cmp,
+                                    "Could not show the claim on the message",
+                                    &e.into(),
+                                    None,
+                                )
+                                .await;
+                                return;
+                            }
+                        };
+                    }
+                    ComponentAction::Delete => {
+                        if let Err(err) = check_backend_state(&ctx, review_id).await {
+                            info!("Not deleting review {}: {:#}", review_id, err);
+                            report_failure(&ctx, &cmp, "Nothing was changed", &err, Some(&retry))
+                                .await;
+                            return;
+                        }
+
+                        let previous =
+                            set_review_state(&ctx, review_id, ModerationState::Deleted).await;
+                        {
                             let gql_client = ctx.data.read().await;
                             let gql_client = gql_client
                                 .get::<MensattGqlClient>()
@@ -242,16 +976,44 @@ impl EventHandler for Handler {
                                 Err(err) => {
                                     warn!("Failed to delete review: {}", err);
                                     warn!("Original message: {:#?}", cmp.message);
+                                    if let Some(previous) = previous {
+                                        set_review_state(&ctx, review_id, previous).await;
+                                    }
+                                    report_failure(
+                                        &ctx,
+                                        &cmp,
+                                        "Could not delete the review",
+                                        &err,
+                                        Some(&retry),
+                                    )
+                                    .await;
                                     return;
                                 }
                             };
                         }
 
+                        audit(
+                            &ctx,
+                            &cmp.user,
+                            AuditAction::Delete,
+                            review_id,
+                            get_dish(&cmp.message),
+                            previous,
+                            None,
+                        )
+                        .await;
+
+                        publish_event(&ctx, ReviewEvent::Deleted(Uuid(review_id.to_string())))
+                            .await;
+                        forget_review(&ctx, review_id).await;
+
                         let msg_edit = EditMessage::new().components(get_action_row(
-                            ReviewMessageState::Delete,
+                            &ReviewMessageState::Delete,
                             review_id,
-                            cmp.message.embeds.first().unwrap().image.is_some(),
-                            cmp.user.name.as_str(),
+                            &get_image_ids(&cmp.message.attachments),
+                            Some(cmp.user.name.as_str()),
+                            None,
+                            Quorum::default(),
                         ));
 
                         match cmp.message.edit(ctx.http.clone(), msg_edit).await {
@@ -259,15 +1021,90 @@ impl EventHandler for Handler {
                             Err(e) => {
                                 warn!("Failed to edit message: {}", e);
                                 warn!("Message: {:#?}", cmp.message);
+                                report_failure(
+                                    &ctx,
+                                    &cmp,
+                                    "The review was deleted, but its message could not be updated",
+                                    &e.into(),
+                                    None,
+                                )
+                                .await;
+                            }
+                        };
+                    }
+                    ComponentAction::Edit => {
+                        let fields = match cmp.message.embeds.first() {
+                            Some(embed) => get_review_fields(embed),
+                            None => {
+                                warn!("Received edit interaction for message without embed");
+                                warn!("Message: {:#?}", cmp.message);
+                                return;
+                            }
+                        };
+
+                        match cmp
+                            .create_response(
+                                ctx.http.clone(),
+                                CreateInteractionResponse::Modal(get_edit_modal(review_id, fields)),
+                            )
+                            .await
+                        {
+                            Ok(_) => {}
+                            Err(err) => {
+                                warn!("Failed to create response: {}", err);
+                                warn!("Message: {:#?}", cmp.message);
+                                return;
+                            }
+                        }
+                    }
+                    ComponentAction::ChooseImage => {
+                        let image_id = match &cmp.data.kind {
+                            ComponentInteractionDataKind::StringSelect { values } => {
+                                values.first().cloned()
+                            }
+                            _ => None,
+                        };
+                        let image_id = match image_id {
+                            Some(image_id) => image_id,
+                            None => {
+                                warn!("Received image choice without an image: {:#?}", cmp.data);
                                 return;
                             }
                         };
+                        let position = get_image_ids(&cmp.message.attachments)
+                            .iter()
+                            .position(|id| *id == image_id)
+                            .map(|i| i + 1);
+
+                        match cmp
+                            .create_response(
+                                ctx.http.clone(),
+                                CreateInteractionResponse::Message(get_image_controls(
+                                    review_id, &image_id, position,
+                                )),
+                            )
+                            .await
+                        {
+                            Ok(_) => {}
+                            Err(err) => {
+                                warn!("Failed to create response: {}", err);
+                                warn!("Message: {:#?}", cmp.message);
+                                return;
+                            }
+                        }
                     }
-                    "edit" => {
+                    ComponentAction::Remove { image_id } => {
+                        let position = get_image_ids(&cmp.message.attachments)
+                            .iter()
+                            .position(|id| id == image_id)
+                            .map(|i| i + 1);
+
                         match cmp
                             .create_response(
                                 ctx.http.clone(),
-                                CreateInteractionResponse::Modal(get_edit_modal("")),
+                                CreateInteractionResponse::Message(get_remove_confirmation(
+                                    review_id, image_id, position,
+                                )),
                             )
                             .await
                         {
@@ -279,36 +1116,100 @@ impl EventHandler for Handler {
                             }
                         }
                     }
-                    "rotate" => {
-                        let angle = split[2].parse::<i32>().unwrap();
-
-                        let image_id: Option<&str> = {
-                            if let Some(embed) = cmp.message.embeds.first() {
-                                if let Some(image) = embed.image.as_ref() {
-                                    Some(
-                                        image
-                                            .url
-                                            .split("/")
-                                            .last()
-                                            .unwrap_or_else(|| {
-                                                panic!("Could not split image url: {}", image.url)
-                                            })
-                                            .split("?")
-                                            .next()
-                                            .unwrap_or_else(|| {
-                                                panic!("Could not split image url: {}", image.url)
-                                            }),
-                                    )
-                                } else {
-                                    None // I don't know why None at the end isn't enough
-                                }
-                            } else {
-                                None
+                    ComponentAction::CancelRemove => {
+                        match cmp
+                            .create_response(
+                                ctx.http.clone(),
+                                CreateInteractionResponse::UpdateMessage(
+                                    CreateInteractionResponseMessage::new()
+                                        .content("The image was not removed")
+                                        .components(vec![]),
+                                ),
+                            )
+                            .await
+                        {
+                            Ok(_) => {}
+                            Err(err) => {
+                                warn!("Failed to create response: {}", err);
+                                return;
+                            }
+                        }
+                    }
+                    ComponentAction::ConfirmRemove { image_id } => {
+                        // The confirmation is the message of this interaction, not the review
+                        match cmp
+                            .create_response(
+                                ctx.http.clone(),
+                                CreateInteractionResponse::UpdateMessage(
+                                    CreateInteractionResponseMessage::new()
+                                        .content("Removing image...")
+                                        .components(vec![]),
+                                ),
+                            )
+                            .await
+                        {
+                            Ok(_) => {}
+                            Err(err) => {
+                                warn!("Failed to create response: {}", err);
+                                return;
+                            }
+                        }
+
+                        let content = match remove_image(&ctx, review_id, image_id).await {
+                            Ok(review) => {
+                                let previous = get_review_state(&ctx, review_id).await;
+                                audit(
+                                    &ctx,
+                                    &cmp.user,
+                                    AuditAction::RemoveImage,
+                                    review_id,
+                                    Some(review.occurrence.dish.name_de),
+                                    previous,
+                                    None,
+                                )
+                                .await;
+                                "The image was removed".to_string()
+                            }
+                            Err(err) => {
+                                warn!(
+                                    "Failed to remove image {} from review {}: {}",
+                                    image_id, review_id, err
+                                );
+                                format!("Could not remove the image: {}", describe_error(&err))
                             }
                         };
 
-                        if let Some(image_id) = image_id {
-                            {
+                        match cmp
+                            .edit_response(
+                                ctx.http.clone(),
+                                EditInteractionResponse::new().content(content),
+                            )
+                            .await
+                        {
+                            Ok(_) => {}
+                            Err(err) => {
+                                warn!("Failed to edit response: {}", err);
+                                return;
+                            }
+                        }
+                    }
+                    ComponentAction::Rotate { image_id, angle } => {
+                        // The image is part of the id, as every image has its own controls
+                        let angle = *angle;
+
+                        let old_attachment = cmp
+                            .message
+                            .attachments
+                            .iter()
+                            .find(|attachment| {
+                                get_attachment_image_id(&attachment.filename) == image_id
+                            })
+                            .map(|attachment| attachment.id);
+                        let is_embedded = cmp.message.attachments.is_empty()
+                            && get_embed_image_id(&cmp.message).as_deref() == Some(image_id);
+
+                        if old_attachment.is_some() || is_embedded {
+                            let attachment = {
                                 let image_client = ctx.data.read().await;
                                 let image_client = image_client
                                     .get::<ImageClient>()
@@ -326,41 +1227,97 @@ impl EventHandler for Handler {
                                             image_id, angle, err
                                         );
                                         warn!("Message: {:#?}", cmp.message);
+                                        report_failure(
+                                            &ctx,
+                                            &cmp,
+                                            "Could not rotate the image",
+                                            &err,
+                                            Some(&retry),
+                                        )
+                                        .await;
                                         return;
                                     }
                                 };
-                            }
 
-                            // Update to fake image
-                            let embed = cmp
-                                .message
-                                .embeds
-                                .first()
-                                .unwrap_or_else(|| {
-                                    panic!("Received message without embed: {:#?}", cmp.message)
-                                })
-                                .clone();
-
-                            let updated_embed = {
-                                let settings = ctx.data.read().await;
-                                let settings = settings
-                                    .get::<Settings>()
-                                    .expect("Could not retrieve Settings from global context");
-
-                                CreateEmbed::from(embed).image(format!(
-                                    "{}{}?auth={}&discord_fake={}",
-                                    settings.image.image_url,
-                                    image_id,
-                                    settings.image.key,
-                                    rand::random::<u64>()
-                                ))
+                                // Discord keeps its own copy of the image, so upload it again
+                                match get_image_attachment(image_client, image_id).await {
+                                    Ok(attachment) => attachment,
+                                    Err(err) => {
+                                        warn!(
+                                            "Failed to download rotated image {}: {}",
+                                            image_id, err
+                                        );
+                                        report_failure(
+                                            &ctx,
+                                            &cmp,
+                                            "The image was rotated, but could not be shown again",
+                                            &err,
+                                            None,
+                                        )
+                                        .await;
+                                        return;
+                                    }
+                                }
+                            };
+
+                            let previous = get_review_state(&ctx, review_id).await;
+                            audit(
+                                &ctx,
+                                &cmp.user,
+                                AuditAction::Rotate,
+                                review_id,
+                                get_dish(&cmp.message),
+                                previous,
+                                None,
+                            )
+                            .await;
+
+                            let embed = match cmp.message.embeds.first() {
+                                Some(embed) => embed.clone(),
+                                None => {
+                                    warn!("Received message without embed: {:#?}", cmp.message);
+                                    send_ephemeral_followup(
+                                        &ctx,
+                                        &cmp,
+                                        "The image was rotated, but the message has no review \
+                                         to show it with anymore",
+                                    )
+                                    .await;
+                                    return;
+                                }
                             };
 
+                            // The new attachment takes the place of the old one, so that the
+                            // order of the gallery stays the same
+                            let mut attachments = EditAttachments::new();
+                            let mut filenames = vec![];
+                            for existing in &cmp.message.attachments {
+                                if Some(existing.id) == old_attachment {
+                                    filenames.push(attachment.filename.clone());
+                                    attachments = attachments.add(attachment.clone());
+                                } else {
+                                    filenames.push(existing.filename.clone());
+                                    attachments = attachments.keep(existing.id);
+                                }
+                            }
+                            // Old messages link the image instead, it is attached from now on
+                            if is_embedded {
+                                filenames.push(attachment.filename.clone());
+                                attachments = attachments.add(attachment.clone());
+                            }
+
+                            let url = embed.url.clone();
+                            let embeds = create_gallery(
+                                CreateEmbed::from(embed),
+                                url.as_deref(),
+                                &filenames,
+                            );
+
                             match cmp
                                 .message
                                 .edit(
                                     ctx.http.clone(),
-                                    EditMessage::new().embeds(vec![updated_embed]),
+                                    EditMessage::new().embeds(embeds).attachments(attachments),
                                 )
                                 .await
                             {
@@ -373,11 +1330,19 @@ impl EventHandler for Handler {
                                 Err(err) => {
                                     warn!("Failed to edit message on rotate: {}", err);
                                     warn!("Message: {:#?}", cmp.message);
+                                    report_failure(
+                                        &ctx,
+                                        &cmp,
+                                        "The image was rotated, but could not be shown again",
+                                        &err.into(),
+                                        None,
+                                    )
+                                    .await;
                                     return;
                                 }
                             };
                         } else {
-                            info!("Tried to rotate image without image id!");
+                            info!("Tried to rotate image {} that is not attached", image_id);
                             debug!("Message: {:#?}", cmp.message);
                             match cmp
                                 .create_followup(
@@ -397,114 +1362,559 @@ impl EventHandler for Handler {
                             return;
                         }
                     }
-                    _ => {
-                        warn!(
-                            "Received component interaction with invalid custom id: {}",
-                            cmp.data.custom_id
-                        );
-                        warn!("Message: {:#?}", cmp.message);
-                        return;
-                    }
                 }
             }
-            Interaction::Modal(modal) => {
+            Interaction::Modal(mut modal) => {
                 info!("Received modal interaction: {:#?}", modal);
-            }
-            _ => warn!("Received unknown interaction: {:#?}", interaction),
-        }
-    }
-}
 
-fn get_edit_modal(review_id: &str) -> CreateModal {
-    CreateModal::new(format!("edit_{}", review_id), "Edit Review").components(vec![
-        CreateActionRow::InputText(
-            CreateInputText::new(InputTextStyle::Short, "Author", "author_field").value("<title>"),
-        ),
-        CreateActionRow::InputText(
-            CreateInputText::new(InputTextStyle::Paragraph, "Description", "desc_field")
-                .value("<description>"),
-        ),
-    ])
-}
+                let custom_id = modal.data.custom_id.parse::<CustomId>();
+                let review_id = match custom_id {
+                    Ok(CustomId {
+                        review_id,
+                        action: ComponentAction::Edit,
+                        ..
+                    }) => review_id,
+                    Ok(CustomId {
+                        review_id,
+                        action: ComponentAction::Reason,
+                        ..
+                    }) => {
+                        if !is_permitted(&ctx, Action::Reject, modal.member.as_ref()).await {
+                            info!(
+                                "Refused rejection of {} on review {}",
+                                modal.user.name, review_id
+                            );
+                            match modal
+                                .create_response(ctx.http.clone(), get_refusal(Action::Reject))
+                                .await
+                            {
+                                Ok(_) => {}
+                                Err(err) => {
+                                    warn!("Failed to create response: {}", err);
+                                }
+                            }
+                            return;
+                        }
 
-fn get_action_row(
-    state: ReviewMessageState,
-    review_id: &str,
-    has_image: bool,
-    who: &str,
-) -> Vec<CreateActionRow> {
-    let mut buttons: Vec<CreateButton> = vec![];
+                        let reason = modal
+                            .data
+                            .components
+                            .iter()
+                            .flat_map(|row| row.components.iter())
+                            .find_map(|component| match component {
+                                ActionRowComponent::InputText(input)
+                                    if input.custom_id == REJECT_REASON_FIELD =>
+                                {
+                                    input.value.clone()
+                                }
+                                _ => None,
+                            })
+                            .map(|reason| reason.trim().to_string())
+                            .filter(|reason| !reason.is_empty())
+                            .unwrap_or(OTHER_REASON.to_string());
 
-    let mut approve_btn = CreateButton::new(format!("approve_{}", review_id))
-        .label("Approve")
-        .emoji(ReactionType::Unicode("✅".to_string()))
-        .style(ButtonStyle::Success);
+                        // The modal was opened from the menu, which is replaced by the result
+                        match modal
+                            .create_response(
+                                ctx.http.clone(),
+                                CreateInteractionResponse::UpdateMessage(
+                                    CreateInteractionResponseMessage::new()
+                                        .content("Rejecting review...")
+                                        .components(vec![]),
+                                ),
+                            )
+                            .await
+                        {
+                            Ok(_) => {}
+                            Err(err) => {
+                                warn!("Failed to create response: {}", err);
+                                return;
+                            }
+                        }
 
-    let mut reject_btn = CreateButton::new(format!("reject_{}", review_id))
-        .label("Reject")
-        .emoji(ReactionType::Unicode("🗑".to_string()))
-        .style(ButtonStyle::Danger);
+                        let response = get_rejection_result(
+                            &review_id,
+                            &reason,
+                            reject_review(&ctx, &modal.user, &review_id, &reason).await,
+                        );
+                        match modal.edit_response(ctx.http.clone(), response).await {
+                            Ok(_) => {}
+                            Err(err) => {
+                                warn!("Failed to edit response: {}", err);
+                            }
+                        }
+                        return;
+                    }
+                    Ok(_) => {
+                        warn!(
+                            "Received modal interaction for unexpected action: {}",
+                            modal.data.custom_id
+                        );
+                        return;
+                    }
+                    Err(err) => {
+                        warn!(
+                            "Received modal interaction with invalid custom id {}: {}",
+                            modal.data.custom_id, err
+                        );
+                        return;
+                    }
+                };
 
-    match state {
-        ReviewMessageState::New => {}
-        ReviewMessageState::Approve => {
-            approve_btn = approve_btn
-                .label(format!("Approved by {}", who))
-                .disabled(true);
-            reject_btn = reject_btn.label("Unapprove");
-        }
-        ReviewMessageState::Unapprove => {
-            reject_btn = reject_btn.label(format!("Reject (unapproved by {})", who))
-        }
-        ReviewMessageState::Reject => {
+                // Checked again, as the roles may have changed while the modal was open
+                if !is_permitted(&ctx, Action::Edit, modal.member.as_ref()).await {
+                    info!(
+                        "Refused edit of {} on review {}",
+                        modal.user.name, review_id
+                    );
+                    match modal
+                        .create_response(ctx.http.clone(), get_refusal(Action::Edit))
+                        .await
+                    {
+                        Ok(_) => {}
+                        Err(err) => {
+                            warn!("Failed to create response: {}", err);
+                        }
+                    }
+                    return;
+                }
+
+                let inputs = modal
+                    .data
+                    .components
+                    .iter()
+                    .flat_map(|row| row.components.iter())
+                    .filter_map(|component| match component {
+                        ActionRowComponent::InputText(input) => Some((
+                            input.custom_id.clone(),
+                            input.value.clone().unwrap_or_default().trim().to_string(),
+                        )),
+                        _ => None,
+                    })
+                    .collect::<HashMap<_, _>>();
+
+                let stars = match inputs
+                    .get(EDIT_STARS_FIELD)
+                    .and_then(|stars| stars.parse::<i32>().ok())
+                {
+                    Some(stars) if (1..=5).contains(&stars) => stars,
+                    _ => {
+                        match modal
+                            .create_response(
+                                ctx.http.clone(),
+                                CreateInteractionResponse::Message(
+                                    CreateInteractionResponseMessage::new()
+                                        .ephemeral(true)
+                                        .content("Stars must be a number between 1 and 5"),
+                                ),
+                            )
+                            .await
+                        {
+                            Ok(_) => {}
+                            Err(err) => {
+                                warn!("Failed to create response: {}", err);
+                            }
+                        }
+                        return;
+                    }
+                };
+                let display_name = inputs
+                    .get(EDIT_DISPLAY_NAME_FIELD)
+                    .cloned()
+                    .unwrap_or_default();
+                let text = inputs.get(EDIT_TEXT_FIELD).cloned().unwrap_or_default();
+
+                // Only send what was actually changed, so that e.g. an anonymous review does not
+                // suddenly get an empty display name
+                let original = modal
+                    .message
+                    .as_ref()
+                    .and_then(|msg| msg.embeds.first())
+                    .map(get_review_fields);
+                let (display_name, text, stars) = match original {
+                    Some(original) => (
+                        (original.display_name.unwrap_or_default() != display_name)
+                            .then_some(display_name),
+                        (original.text.unwrap_or_default() != text).then_some(text),
+                        (original.stars != stars).then_some(stars),
+                    ),
+                    None => (Some(display_name), Some(text), Some(stars)),
+                };
+
+                match modal.defer(ctx.http.clone()).await {
+                    Ok(_) => {}
+                    Err(e) => {
+                        warn!("Failed to defer modal: {}", e);
+                        return;
+                    }
+                }
+
+                if display_name.is_none() && text.is_none() && stars.is_none() {
+                    info!("Review {} was not changed, nothing to edit", review_id);
+                    return;
+                }
+
+                if let Err(err) = check_backend_state(&ctx, &review_id).await {
+                    info!("Not editing review {}: {:#}", review_id, err);
+                    match modal
+                        .create_followup(
+                            ctx.http.clone(),
+                            CreateInteractionResponseFollowup::new()
+                                .ephemeral(true)
+                                .content(format!("Nothing was changed: {}", describe_error(&err))),
+                        )
+                        .await
+                    {
+                        Ok(_) => {}
+                        Err(err) => {
+                            warn!("Failed to create followup: {}", err);
+                        }
+                    }
+                    return;
+                }
+
+                let (review, settings) = {
+                    let guard = ctx.data.read().await;
+                    let gql_client = guard
+                        .get::<MensattGqlClient>()
+                        .expect("Could not retrieve MensattGqlClient from global context");
+                    let settings = guard
+                        .get::<Settings>()
+                        .expect("Could not retrieve settings from global context");
+                    (
+                        gql_client
+                            .edit_review(Uuid(review_id.clone()), display_name, text, stars)
+                            .await,
+                        settings.clone(),
+                    )
+                };
+
+                let review = match review {
+                    Ok(review) => review,
+                    Err(err) => {
+                        warn!("Failed to edit review: {}", err);
+                        warn!("Original message: {:#?}", modal.message);
+                        match modal
+                            .create_followup(
+                                ctx.http.clone(),
+                                CreateInteractionResponseFollowup::new()
+                                    .ephemeral(true)
+                                    .content(format!(
+                                        "Could not edit the review: {}",
+                                        describe_error(&err)
+                                    )),
+                            )
+                            .await
+                        {
+                            Ok(_) => {}
+                            Err(err) => {
+                                warn!("Failed to create followup: {}", err);
+                            }
+                        }
+                        return;
+                    }
+                };
+
+                let previous = get_review_state(&ctx, &review_id).await;
+                audit(
+                    &ctx,
+                    &modal.user,
+                    AuditAction::Edit,
+                    &review_id,
+                    Some(review.occurrence.dish.name_de.clone()),
+                    previous,
+                    None,
+                )
+                .await;
+
+                publish_event(&ctx, ReviewEvent::Updated(review.clone())).await;
+
+                if let Some(msg) = modal.message.as_mut() {
+                    let filenames = get_filenames(&msg.attachments);
+                    match msg
+                        .edit(
+                            ctx.http.clone(),
+                            EditMessage::new()
+                                .embeds(create_embeds(&settings, &review, &filenames)),
+                        )
+                        .await
+                    {
+                        Ok(_) => {}
+                        Err(e) => {
+                            warn!("Failed to edit message: {}", e);
+                            warn!("Message: {:#?}", msg);
+                        }
+                    };
+                }
+            }
+            _ => warn!("Received unknown interaction: {:#?}", interaction),
+        }
+    }
+}
+
+/// Reads the dish back from the title of an embed built by [`create_embed`]
+fn get_dish(message: &Message) -> Option<String> {
+    message
+        .embeds
+        .first()
+        .and_then(|embed| embed.title.as_deref())
+        .and_then(|title| title.rsplit_once(" | "))
+        .map(|(dish, _)| dish.to_string())
+}
+
+/// Reads the current review contents back from an embed built by [`create_embed`]
+fn get_review_fields(embed: &Embed) -> ReviewFields {
+    ReviewFields {
+        display_name: embed
+            .author
+            .as_ref()
+            .map(|author| author.name.clone())
+            .filter(|name| name != "Anonymous"),
+        text: embed.description.clone(),
+        stars: embed
+            .title
+            .as_deref()
+            .and_then(|title| title.rsplit(" | ").next())
+            .map(|stars| stars.chars().filter(|c| *c == '★').count() as i32)
+            .unwrap_or(0),
+    }
+}
+
+fn get_edit_modal(review_id: &str, fields: ReviewFields) -> CreateModal {
+    CreateModal::new(custom_id(review_id, ComponentAction::Edit), "Edit Review").components(vec![
+        CreateActionRow::InputText(
+            CreateInputText::new(
+                InputTextStyle::Short,
+                "Display name",
+                EDIT_DISPLAY_NAME_FIELD,
+            )
+            .value(fields.display_name.unwrap_or_default())
+            .required(false),
+        ),
+        CreateActionRow::InputText(
+            CreateInputText::new(InputTextStyle::Paragraph, "Text", EDIT_TEXT_FIELD)
+                .value(fields.text.unwrap_or_default())
+                .required(false),
+        ),
+        CreateActionRow::InputText(
+            CreateInputText::new(InputTextStyle::Short, "Stars (1-5)", EDIT_STARS_FIELD)
+                .value(fields.stars.to_string())
+                .min_length(1)
+                .max_length(1),
+        ),
+    ])
+}
+
+fn get_reason_menu(review_id: &str, reasons: &[String]) -> CreateInteractionResponseMessage {
+    let options = reasons
+        .iter()
+        .take(MAX_REJECT_REASONS)
+        .chain([OTHER_REASON.to_string()].iter())
+        .map(|reason| CreateSelectMenuOption::new(reason, reason))
+        .collect();
+
+    CreateInteractionResponseMessage::new()
+        .ephemeral(true)
+        .content("Why is the review rejected?")
+        .components(vec![CreateActionRow::SelectMenu(
+            CreateSelectMenu::new(
+                custom_id(review_id, ComponentAction::Reason),
+                CreateSelectMenuKind::String { options },
+            )
+            .placeholder("Reason"),
+        )])
+}
+
+fn get_reason_modal(review_id: &str) -> CreateModal {
+    CreateModal::new(
+        custom_id(review_id, ComponentAction::Reason),
+        "Reject Review",
+    )
+    .components(vec![CreateActionRow::InputText(
+        CreateInputText::new(InputTextStyle::Short, "Reason", REJECT_REASON_FIELD).max_length(60),
+    )])
+}
+
+fn truncate_label(label: String) -> String {
+    if label.chars().count() <= MAX_LABEL_LEN {
+        return label;
+    }
+    label.chars().take(MAX_LABEL_LEN - 1).collect::<String>() + "…"
+}
+
+fn custom_id(review_id: &str, action: ComponentAction) -> String {
+    CustomId::new(review_id, action).to_string()
+}
+
+fn get_action_row(
+    state: &ReviewMessageState,
+    review_id: &str,
+    image_ids: &[String],
+    who: Option<&str>,
+    reason: Option<&str>,
+    quorum: Quorum,
+) -> Vec<CreateActionRow> {
+    // Without a user, the action was taken outside of discord (e.g. in the mensatt admin panel)
+    let by = who
+        .map(|who| format!("by {}", who))
+        .unwrap_or("externally".to_string());
+
+    let mut approve_btn = CreateButton::new(custom_id(review_id, ComponentAction::Approve))
+        .label(quorum.label())
+        .emoji(ReactionType::Unicode("✅".to_string()))
+        .style(ButtonStyle::Success);
+
+    let mut reject_btn = CreateButton::new(custom_id(review_id, ComponentAction::Reject))
+        .label("Reject")
+        .emoji(ReactionType::Unicode("🗑".to_string()))
+        .style(ButtonStyle::Danger);
+
+    match state {
+        ReviewMessageState::New => {}
+        ReviewMessageState::Approve => {
+            approve_btn = approve_btn.label(format!("Approved {}", by)).disabled(true);
             reject_btn = reject_btn
-                .label(format!("Delete (rejected by {})", who))
-                .custom_id(format!("delete_{}", review_id));
+                .label("Unapprove")
+                .custom_id(custom_id(review_id, ComponentAction::Unapprove));
+        }
+        ReviewMessageState::Unapprove => {
+            reject_btn = reject_btn.label(format!("Reject (unapproved {})", by))
+        }
+        ReviewMessageState::Reject => {
+            let label = match reason {
+                Some(reason) => format!("Delete (rejected {}: {})", by, reason),
+                None => format!("Delete (rejected {})", by),
+            };
+            reject_btn = reject_btn
+                .label(truncate_label(label))
+                .custom_id(custom_id(review_id, ComponentAction::Delete));
         }
         ReviewMessageState::Delete => {
             reject_btn = reject_btn
-                .label(format!("Deleted by {}", who))
+                .label(format!("Deleted {}", by))
                 .disabled(true)
-                .custom_id(format!("_____reject_deleted_{}", review_id));
-            approve_btn = approve_btn
-                .disabled(true)
-                .custom_id(format!("_____approve_deleted_{}", review_id));
+                .custom_id(custom_id(review_id, ComponentAction::Delete));
+            approve_btn = approve_btn.disabled(true);
         }
     }
 
-    let mut rotation_btns = vec![
-        CreateButton::new(format!("rotate_{}_270", review_id))
-            .emoji(ReactionType::Unicode("↪".to_string()))
-            .style(ButtonStyle::Secondary)
-            .disabled(state == ReviewMessageState::Delete),
-        CreateButton::new(format!("rotate_{}_180", review_id))
-            .emoji(ReactionType::Unicode("↕".to_string()))
-            .style(ButtonStyle::Secondary)
-            .disabled(state == ReviewMessageState::Delete),
-        CreateButton::new(format!("rotate_{}_90", review_id))
-            .emoji(ReactionType::Unicode("↩".to_string()))
-            .style(ButtonStyle::Secondary)
-            .disabled(state == ReviewMessageState::Delete),
-    ];
+    let edit_btn = CreateButton::new(custom_id(review_id, ComponentAction::Edit))
+        .label("Edit")
+        .emoji(ReactionType::Unicode("✏".to_string()))
+        .style(ButtonStyle::Secondary)
+        .disabled(*state == ReviewMessageState::Delete);
+
+    // Any change of the state ends the claim, so it is never shown as claimed here
+    let claim_btn = CreateButton::new(custom_id(review_id, ComponentAction::Claim))
+        .label("Claim")
+        .emoji(ReactionType::Unicode("🙋".to_string()))
+        .style(ButtonStyle::Secondary)
+        .disabled(*state == ReviewMessageState::Delete);
+
+    let mut rows = vec![CreateActionRow::Buttons(vec![
+        approve_btn,
+        reject_btn,
+        edit_btn,
+        claim_btn,
+    ])];
+
+    // Discord allows up to 5 rows per message, too few to give every image its own controls
+    if !image_ids.is_empty() {
+        rows.push(CreateActionRow::SelectMenu(get_image_menu(
+            custom_id(review_id, ComponentAction::ChooseImage),
+            &image_ids[..image_ids.len().min(MAX_EMBEDS)],
+            *state == ReviewMessageState::Delete,
+        )));
+    }
+
+    rows
+}
+
+fn get_image_menu(custom_id: String, image_ids: &[String], disabled: bool) -> CreateSelectMenu {
+    let options = image_ids
+        .iter()
+        .enumerate()
+        .map(|(i, image_id)| CreateSelectMenuOption::new(format!("Image {}", i + 1), image_id))
+        .collect();
+
+    CreateSelectMenu::new(custom_id, CreateSelectMenuKind::String { options })
+        .placeholder("Rotate or remove an image")
+        .disabled(disabled)
+}
 
-    buttons.push(approve_btn);
-    if has_image {
-        buttons.append(&mut rotation_btns);
+/// Copies the menu of images of a message, leaving out the removed image
+fn copy_image_menu(menu: &SelectMenu, removed: Option<&str>) -> Option<CreateSelectMenu> {
+    let image_ids = menu
+        .options
+        .iter()
+        .map(|option| option.value.clone())
+        .filter(|image_id| Some(image_id.as_str()) != removed)
+        .collect::<Vec<_>>();
+    if image_ids.is_empty() {
+        return None;
     }
-    buttons.push(reject_btn);
 
-    vec![CreateActionRow::Buttons(buttons)]
+    Some(get_image_menu(
+        menu.custom_id.clone().unwrap_or_default(),
+        &image_ids,
+        menu.disabled,
+    ))
+}
+
+fn get_image_controls(
+    review_id: &str,
+    image_id: &str,
+    position: Option<usize>,
+) -> CreateInteractionResponseMessage {
+    let rotate_btn = |angle: i32, emoji: &str| {
+        CreateButton::new(custom_id(
+            review_id,
+            ComponentAction::Rotate {
+                image_id: image_id.to_string(),
+                angle,
+            },
+        ))
+        .emoji(ReactionType::Unicode(emoji.to_string()))
+        .style(ButtonStyle::Secondary)
+    };
+
+    let image = position
+        .map(|position| format!("Image {}", position))
+        .unwrap_or("This image".to_string());
+
+    CreateInteractionResponseMessage::new()
+        .ephemeral(true)
+        .content(format!("{} can be rotated or removed here", image))
+        .components(vec![CreateActionRow::Buttons(vec![
+            rotate_btn(270, "↪"),
+            rotate_btn(180, "↕"),
+            rotate_btn(90, "↩"),
+            CreateButton::new(custom_id(
+                review_id,
+                ComponentAction::Remove {
+                    image_id: image_id.to_string(),
+                },
+            ))
+            .label("Remove")
+            .emoji(ReactionType::Unicode("🚫".to_string()))
+            .style(ButtonStyle::Danger),
+        ])])
 }
 
-fn create_review_embed(settings: &Settings, review: Review) -> CreateMessage {
+fn create_embed(settings: &Settings, review: &Review) -> CreateEmbed {
     let mut embed = CreateEmbed::new()
         .author(CreateEmbedAuthor::new(
-            review.display_name.unwrap_or("Anonymous".to_string()),
+            review
+                .display_name
+                .clone()
+                .filter(|name| !name.is_empty())
+                .unwrap_or("Anonymous".to_string()),
         ))
         .colour(Colour::from_rgb(255, 107, 38))
         .timestamp(
             Timestamp::from_str(review.created_at.0.as_str()).unwrap_or_else(|_| {
-                panic!("Could not parse review time stamp: {:?}", review.created_at)
+                warn!("Could not parse review time stamp: {:?}", review.created_at);
+                Timestamp::now()
             }),
         )
         .title(format!(
@@ -517,75 +1927,1142 @@ fn create_review_embed(settings: &Settings, review: Review) -> CreateMessage {
             settings.mensatt.occurrence_url, review.occurrence.id.0
         ));
 
-    if let Some(text) = review.text {
+    if let Some(text) = review.text.clone().filter(|text| !text.is_empty()) {
         embed = embed.description(text);
     }
 
-    if !review.images.is_empty() {
-        embed = embed.image(format!(
-            "{}{}?auth={}",
-            settings.image.image_url,
-            review.images.first().unwrap().id.0,
-            settings.image.key
-        ));
+    embed
+}
+
+/// The review embed followed by one embed per further image, which discord shows as a gallery
+///
+/// The images are referenced by the filenames of the message's attachments.
+fn create_embeds(settings: &Settings, review: &Review, filenames: &[String]) -> Vec<CreateEmbed> {
+    let url = format!(
+        "{}{}",
+        settings.mensatt.occurrence_url, review.occurrence.id.0
+    );
+    create_gallery(create_embed(settings, review), Some(&url), filenames)
+}
+
+fn create_gallery(embed: CreateEmbed, url: Option<&str>, filenames: &[String]) -> Vec<CreateEmbed> {
+    let mut filenames = filenames
+        .iter()
+        .take(MAX_EMBEDS)
+        .map(|filename| format!("attachment://{}", filename));
+
+    let mut embeds = vec![match filenames.next() {
+        Some(image) => embed.image(image),
+        None => embed,
+    }];
+    // Embeds are only grouped into a gallery if they share the same url
+    for image in filenames {
+        let mut gallery_embed = CreateEmbed::new().image(image);
+        if let Some(url) = url {
+            gallery_embed = gallery_embed.url(url);
+        }
+        embeds.push(gallery_embed);
     }
+    embeds
+}
+
+fn create_review_embed(
+    settings: &Settings,
+    review: Review,
+    attachments: Vec<CreateAttachment>,
+) -> CreateMessage {
+    let filenames = attachments
+        .iter()
+        .map(|attachment| attachment.filename.clone())
+        .collect::<Vec<_>>();
+    let image_ids = filenames
+        .iter()
+        .map(|filename| get_attachment_image_id(filename).to_string())
+        .collect::<Vec<_>>();
 
-    CreateMessage::new().embed(embed).components(get_action_row(
-        ReviewMessageState::New,
-        &review.id.to_string(),
-        !review.images.is_empty(),
-        "invalid", // TODO: Make Option<>
+    CreateMessage::new()
+        .embeds(create_embeds(settings, &review, &filenames))
+        .components(get_action_row(
+            &ReviewMessageState::New,
+            &review.id.to_string(),
+            &image_ids,
+            None,
+            None,
+            Quorum {
+                votes: 0,
+                // Images that couldn't be downloaded aren't shown, just like for existing messages
+                required: settings
+                    .discord
+                    .get_required_approvals(review.stars, !image_ids.is_empty()),
+            },
+        ))
+        .add_files(attachments)
+}
+
+/// Downloads an image, so that it can be uploaded to discord without handing out our key
+async fn get_image_attachment(
+    image_client: &ImageClient,
+    image_id: &str,
+) -> anyhow::Result<CreateAttachment> {
+    let (data, content_type) = image_client.download_image(image_id).await?;
+    // Discord only displays attachments as images if they have a fitting extension
+    let extension = content_type.split('/').nth(1).unwrap_or("jpeg");
+    Ok(CreateAttachment::bytes(
+        data,
+        format!("{}.{}", image_id, extension),
     ))
 }
 
-pub struct Bot {
-    rx: tokio::sync::mpsc::Receiver<Review>,
-    settings: Settings,
-    gql_client: Arc<MensattGqlClient>,
-    image_client: Arc<ImageClient>,
+/// Attachments are named after the image they contain, e.g. `<image id>.jpeg`
+/// Image of messages sent before images were attached, linked as `<image_url><image>?auth=<key>`
+fn get_embed_image_id(message: &Message) -> Option<String> {
+    let image = message.embeds.first()?.image.as_ref()?;
+    let last = image.url.split('/').next_back()?;
+    last.split('?').next().map(|image_id| image_id.to_string())
 }
 
-impl Bot {
-    pub fn new(rx: tokio::sync::mpsc::Receiver<Review>, settings: Settings) -> Self {
-        info!("Creating local graphql and image client");
-        let gql_client = Arc::new(MensattGqlClient::new(settings.clone()));
-        let image_client = Arc::new(ImageClient::new(settings.clone()));
-        Bot {
-            rx,
-            settings,
-            gql_client,
-            image_client,
+/// Whether the message shows images, which is what the quorum depends on
+fn has_images(message: &Message) -> bool {
+    !message.attachments.is_empty() || get_embed_image_id(message).is_some()
+}
+
+fn get_attachment_image_id(filename: &str) -> &str {
+    filename.split('.').next().unwrap_or(filename)
+}
+
+fn get_filenames(attachments: &[Attachment]) -> Vec<String> {
+    attachments
+        .iter()
+        .map(|attachment| attachment.filename.clone())
+        .collect()
+}
+
+fn get_image_ids(attachments: &[Attachment]) -> Vec<String> {
+    attachments
+        .iter()
+        .map(|attachment| get_attachment_image_id(&attachment.filename).to_string())
+        .collect()
+}
+
+fn get_remove_confirmation(
+    review_id: &str,
+    image_id: &str,
+    position: Option<usize>,
+) -> CreateInteractionResponseMessage {
+    let image = position
+        .map(|position| format!("image {}", position))
+        .unwrap_or("this image".to_string());
+
+    CreateInteractionResponseMessage::new()
+        .ephemeral(true)
+        .content(format!(
+            "Do you really want to remove {} from the review? This can not be undone.",
+            image
+        ))
+        .components(vec![CreateActionRow::Buttons(vec![
+            CreateButton::new(custom_id(
+                review_id,
+                ComponentAction::ConfirmRemove {
+                    image_id: image_id.to_string(),
+                },
+            ))
+            .label("Remove image")
+            .emoji(ReactionType::Unicode("🚫".to_string()))
+            .style(ButtonStyle::Danger),
+            CreateButton::new(custom_id(review_id, ComponentAction::CancelRemove))
+                .label("Cancel")
+                .style(ButtonStyle::Secondary),
+        ])])
+}
+
+/// Removes the image from the review and drops it from the review's message
+async fn remove_image(ctx: &Context, review_id: &str, image_id: &str) -> anyhow::Result<Review> {
+    check_backend_state(ctx, review_id).await?;
+
+    let (review, settings, store) = {
+        let guard = ctx.data.read().await;
+        let gql_client = guard
+            .get::<MensattGqlClient>()
+            .expect("Could not retrieve MensattGqlClient from global context");
+        let settings = guard
+            .get::<Settings>()
+            .expect("Could not retrieve settings from global context");
+        let store = guard
+            .get::<ReviewStore>()
+            .expect("Could not retrieve ReviewStore from global context");
+        (
+            gql_client
+                .remove_images_from_review(
+                    Uuid(review_id.to_string()),
+                    vec![Uuid(image_id.to_string())],
+                )
+                .await?,
+            settings.clone(),
+            store.clone(),
+        )
+    };
+
+    let record = store.get(review_id).ok_or_else(|| {
+        anyhow::anyhow!("The image is gone, but the review's message could not be found")
+    })?;
+    let mut msg = ChannelId::new(record.channel_id)
+        .message(&ctx.http, record.message_id)
+        .await?;
+
+    let mut attachments = EditAttachments::new();
+    let mut filenames = vec![];
+    for attachment in &msg.attachments {
+        if get_attachment_image_id(&attachment.filename) != image_id {
+            filenames.push(attachment.filename.clone());
+            attachments = attachments.keep(attachment.id);
         }
     }
 
-    pub async fn listen_for_gql_events(&mut self, http: Arc<Http>) -> anyhow::Result<()> {
-        let comms = ChannelId::new(self.settings.discord.comm_channel);
+    let components = remove_image_controls(&msg.components, image_id);
+    msg.edit(
+        &ctx.http,
+        EditMessage::new()
+            .embeds(create_embeds(&settings, &review, &filenames))
+            .attachments(attachments)
+            .components(components),
+    )
+    .await?;
 
-        while let Some(review) = self.rx.recv().await {
-            info!("Received review through channel: {:#?}", review);
+    // Only after editing, so that the refresh triggered by this doesn't bring the image back
+    publish_event(ctx, ReviewEvent::Updated(review.clone())).await;
+    Ok(review)
+}
 
-            let msg = create_review_embed(&self.settings, review);
+/// What came of rejecting a review
+enum Rejection {
+    Rejected,
+    // The review is rejected, but its message still shows the previous state
+    NotShown(anyhow::Error),
+    Failed(anyhow::Error),
+}
 
-            comms.send_message(http.clone(), msg).await?;
+/// Rejects the review and shows the reason on the review's message
+async fn reject_review(ctx: &Context, user: &User, review_id: &str, reason: &str) -> Rejection {
+    if let Err(err) = check_backend_state(ctx, review_id).await {
+        return Rejection::Failed(err);
+    }
+
+    let previous = set_review_state(ctx, review_id, ModerationState::Rejected).await;
+
+    let result = {
+        let guard = ctx.data.read().await;
+        let gql_client = guard
+            .get::<MensattGqlClient>()
+            .expect("Could not retrieve MensattGqlClient from global context");
+        gql_client
+            .update_review(Uuid(review_id.to_string()), false)
+            .await
+    };
+    if let Err(err) = result {
+        if let Some(previous) = previous {
+            set_review_state(ctx, review_id, previous).await;
         }
+        return Rejection::Failed(err);
+    }
 
-        Ok(())
+    // The message is only needed for the dish, the rejection is recorded without it as well
+    let msg = get_review_message(ctx, review_id).await;
+    audit(
+        ctx,
+        user,
+        AuditAction::Reject,
+        review_id,
+        msg.as_ref().ok().and_then(get_dish),
+        previous,
+        Some(reason.to_string()),
+    )
+    .await;
+    publish_event(ctx, ReviewEvent::Rejected(Uuid(review_id.to_string()))).await;
+
+    let mut msg = match msg {
+        Ok(msg) => msg,
+        Err(err) => return Rejection::NotShown(err),
+    };
+    let image_ids = get_image_ids(&msg.attachments);
+    let quorum = get_quorum(ctx, review_id, &msg).await;
+    match msg
+        .edit(
+            &ctx.http,
+            EditMessage::new().components(get_action_row(
+                &ReviewMessageState::Reject,
+                review_id,
+                &image_ids,
+                Some(user.name.as_str()),
+                Some(reason),
+                quorum,
+            )),
+        )
+        .await
+    {
+        Ok(_) => Rejection::Rejected,
+        Err(err) => Rejection::NotShown(err.into()),
+    }
+}
+
+/// Response telling the moderator how rejecting went, with a button to try again if that may help
+fn get_rejection_result(
+    review_id: &str,
+    reason: &str,
+    rejection: Rejection,
+) -> EditInteractionResponse {
+    match rejection {
+        Rejection::Rejected => {
+            EditInteractionResponse::new().content(format!("Rejected the review: {}", reason))
+        }
+        Rejection::NotShown(err) => {
+            warn!(
+                "Rejected review {}, but could not update its message: {}",
+                review_id, err
+            );
+            EditInteractionResponse::new().content(format!(
+                "Rejected the review: {}\nIts message could not be updated: {}",
+                reason,
+                describe_error(&err)
+            ))
+        }
+        Rejection::Failed(err) => {
+            warn!("Failed to reject review {}: {}", review_id, err);
+            let response = EditInteractionResponse::new().content(format!(
+                "Could not reject the review: {}",
+                describe_error(&err)
+            ));
+            if is_transient(&err) {
+                let retry = CustomId::new(review_id, ComponentAction::Reject).for_retry();
+                response.components(vec![get_retry_row(&retry)])
+            } else {
+                response
+            }
+        }
+    }
+}
+
+fn get_button_action(button: &Button) -> Option<ComponentAction> {
+    match &button.data {
+        ButtonKind::NonLink { custom_id, .. } => custom_id
+            .parse::<CustomId>()
+            .ok()
+            .map(|custom_id| custom_id.action),
+        _ => None,
+    }
+}
+
+/// Copies the components of a message, except for the controls of the removed image
+fn remove_image_controls(rows: &[ActionRow], image_id: &str) -> Vec<CreateActionRow> {
+    let mut image_nr = 0;
+    rows.iter()
+        .filter_map(|row| {
+            if let Some(ActionRowComponent::SelectMenu(menu)) = row.components.first() {
+                return copy_image_menu(menu, Some(image_id)).map(CreateActionRow::SelectMenu);
+            }
+
+            let buttons = row
+                .components
+                .iter()
+                .filter_map(|component| match component {
+                    ActionRowComponent::Button(button) => Some(button.clone()),
+                    _ => None,
+                })
+                .collect::<Vec<_>>();
+
+            // Messages sent before the menu of images existed have a row of buttons per image
+            let actions = buttons
+                .iter()
+                .filter_map(get_button_action)
+                .collect::<Vec<_>>();
+            let is_image_row = actions
+                .iter()
+                .any(|action| matches!(action, ComponentAction::Rotate { .. }));
+            if !is_image_row {
+                return Some(CreateActionRow::Buttons(
+                    buttons.into_iter().map(CreateButton::from).collect(),
+                ));
+            }
+
+            let is_removed = actions.iter().any(|action| match action {
+                ComponentAction::Rotate { image_id: id, .. }
+                | ComponentAction::Remove { image_id: id } => id == image_id,
+                _ => false,
+            });
+            if is_removed {
+                return None;
+            }
+
+            // The labels count the images, which moved up by one after the removed one
+            image_nr += 1;
+            let mut buttons = buttons.into_iter().map(CreateButton::from);
+            let first = buttons
+                .next()
+                .map(|button| button.label(format!("Image {}", image_nr)));
+            Some(CreateActionRow::Buttons(
+                first.into_iter().chain(buttons).collect(),
+            ))
+        })
+        .collect()
+}
+
+async fn get_quorum(ctx: &Context, review_id: &str, message: &Message) -> Quorum {
+    let guard = ctx.data.read().await;
+    let settings = guard
+        .get::<Settings>()
+        .expect("Could not retrieve settings from global context");
+    let store = guard
+        .get::<ReviewStore>()
+        .expect("Could not retrieve ReviewStore from global context");
+    get_message_quorum(settings, store, review_id, message)
+}
+
+/// Reads the votes from the store and what is needed from the review's message
+fn get_message_quorum(
+    settings: &Settings,
+    store: &ReviewStore,
+    review_id: &str,
+    message: &Message,
+) -> Quorum {
+    let stars = message
+        .embeds
+        .first()
+        .map(|embed| get_review_fields(embed).stars)
+        .unwrap_or(0);
+    Quorum {
+        votes: store
+            .get(review_id)
+            .map(|record| record.approvals.len())
+            .unwrap_or(0),
+        required: settings
+            .discord
+            .get_required_approvals(stars, has_images(message)),
+    }
+}
+
+/// Counts the user's approval. Once the review has enough approvals, it is marked as approved and
+/// the state it had before is returned.
+async fn vote(
+    ctx: &Context,
+    cmp: &ComponentInteraction,
+    review_id: &str,
+) -> Option<Option<ModerationState>> {
+    let quorum = get_quorum(ctx, review_id, &cmp.message).await;
+    if quorum.required <= 1 {
+        return Some(set_review_state(ctx, review_id, ModerationState::Approved).await);
+    }
+
+    let store = {
+        let guard = ctx.data.read().await;
+        guard
+            .get::<ReviewStore>()
+            .expect("Could not retrieve ReviewStore from global context")
+            .clone()
+    };
+    let votes = match store
+        .add_approval(review_id, cmp.user.id.get(), quorum.required)
+        .await
+    {
+        Ok(Vote::Counted(votes)) => votes,
+        Ok(Vote::Reached(previous)) => return Some(Some(previous)),
+        Ok(Vote::Repeated) => {
+            send_ephemeral_followup(ctx, cmp, "You already voted to approve this review").await;
+            return None;
+        }
+        Ok(Vote::Closed) => {
+            send_ephemeral_followup(ctx, cmp, "This review was approved by the others already")
+                .await;
+            return None;
+        }
+        Ok(Vote::UnknownReview) => {
+            send_ephemeral_followup(
+                ctx,
+                cmp,
+                "Votes can't be counted for this review, as it was sent before they were",
+            )
+            .await;
+            return None;
+        }
+        Err(err) => {
+            warn!("Could not count vote on review {}: {}", review_id, err);
+            send_ephemeral_followup(ctx, cmp, "Could not count your vote").await;
+            return None;
+        }
+    };
+    let previous = get_review_state(ctx, review_id).await;
+    audit(
+        ctx,
+        &cmp.user,
+        AuditAction::Vote,
+        review_id,
+        get_dish(&cmp.message),
+        previous,
+        None,
+    )
+    .await;
+
+    let quorum = Quorum {
+        votes,
+        required: quorum.required,
+    };
+    let components = edit_button(
+        &cmp.message.components,
+        ComponentAction::Approve,
+        |button| button.label(quorum.label()),
+    );
+    match cmp
+        .message
+        .clone()
+        .edit(&ctx.http, EditMessage::new().components(components))
+        .await
+    {
+        Ok(_) => {}
+        Err(err) => {
+            warn!("Failed to edit message: {}", err);
+            warn!("Message: {:#?}", cmp.message);
+        }
+    }
+
+    None
+}
+
+async fn remove_vote(ctx: &Context, review_id: &str, user_id: u64) {
+    let guard = ctx.data.read().await;
+    let store = guard
+        .get::<ReviewStore>()
+        .expect("Could not retrieve ReviewStore from global context");
+    if let Err(err) = store.remove_approval(review_id, user_id).await {
+        warn!("Could not remove vote on review {}: {}", review_id, err);
+    }
+}
+
+/// Copies the components of a message, changing the button for the action
+fn edit_button(
+    rows: &[ActionRow],
+    action: ComponentAction,
+    edit: impl Fn(CreateButton) -> CreateButton,
+) -> Vec<CreateActionRow> {
+    rows.iter()
+        .filter_map(|row| {
+            if let Some(ActionRowComponent::SelectMenu(menu)) = row.components.first() {
+                return copy_image_menu(menu, None).map(CreateActionRow::SelectMenu);
+            }
+
+            Some(CreateActionRow::Buttons(
+                row.components
+                    .iter()
+                    .filter_map(|component| match component {
+                        ActionRowComponent::Button(button) => Some(button.clone()),
+                        _ => None,
+                    })
+                    .map(|button| {
+                        if get_button_action(&button).as_ref() == Some(&action) {
+                            edit(CreateButton::from(button))
+                        } else {
+                            CreateButton::from(button)
+                        }
+                    })
+                    .collect(),
+            ))
+        })
+        .collect()
+}
+
+/// The active claim of the review, unless it is the user's own
+async fn get_foreign_claim(ctx: &Context, review_id: &str, user_id: u64) -> Option<Claim> {
+    let guard = ctx.data.read().await;
+    guard
+        .get::<ReviewStore>()
+        .expect("Could not retrieve ReviewStore from global context")
+        .get(review_id)
+        .and_then(|record| record.claim)
+        .filter(|claim| claim.is_active() && claim.user_id != user_id)
+}
+
+fn get_claim_message(claim: &Claim) -> String {
+    format!(
+        "This review is claimed by {} until <t:{}:t>",
+        claim.user_name,
+        claim.until.timestamp()
+    )
+}
+
+/// Makes sure that the review is still in the state we know of, so that nobody moderates based
+/// on an outdated message
+async fn check_backend_state(ctx: &Context, review_id: &str) -> anyhow::Result<()> {
+    let (approved, state) = {
+        let guard = ctx.data.read().await;
+        let gql_client = guard
+            .get::<MensattGqlClient>()
+            .expect("Could not retrieve MensattGqlClient from global context");
+        let store = guard
+            .get::<ReviewStore>()
+            .expect("Could not retrieve ReviewStore from global context");
+        let state = store.get(review_id).map(|record| record.state);
+        (
+            gql_client
+                .is_review_approved(
+                    &Uuid(review_id.to_string()),
+                    state == Some(ModerationState::Approved),
+                )
+                .await
+                .context("Could not check the current state of the review")?,
+            state,
+        )
+    };
+
+    match (approved, state) {
+        (None, _) => anyhow::bail!("The review was deleted in the meantime"),
+        (Some(true), Some(state)) if state != ModerationState::Approved => {
+            anyhow::bail!("The review was approved in the meantime")
+        }
+        (Some(false), Some(ModerationState::Approved)) => {
+            anyhow::bail!("The review was unapproved in the meantime")
+        }
+        _ => Ok(()),
+    }
+}
+
+async fn send_ephemeral_followup(ctx: &Context, cmp: &ComponentInteraction, content: &str) {
+    match cmp
+        .create_followup(
+            ctx.http.clone(),
+            CreateInteractionResponseFollowup::new()
+                .ephemeral(true)
+                .content(content),
+        )
+        .await
+    {
+        Ok(_) => {}
+        Err(err) => {
+            warn!("Failed to create followup: {}", err);
+        }
+    }
+}
+
+/// The message a review was posted with, looked up through the store
+async fn get_review_message(ctx: &Context, review_id: &str) -> anyhow::Result<Message> {
+    let record = {
+        let data = ctx.data.read().await;
+        data.get::<ReviewStore>()
+            .expect("Could not retrieve ReviewStore from global context")
+            .get(review_id)
+    };
+    let record = record.ok_or_else(|| anyhow::anyhow!("Review {} is not known", review_id))?;
+
+    Ok(ChannelId::new(record.channel_id)
+        .message(&ctx.http, MessageId::new(record.message_id))
+        .await?)
+}
+
+/// Tells the moderator what went wrong, with a button to try again if `retry` is given and trying
+/// again may help
+async fn report_failure(
+    ctx: &Context,
+    cmp: &ComponentInteraction,
+    what: &str,
+    err: &anyhow::Error,
+    retry: Option<&CustomId>,
+) {
+    let mut followup = CreateInteractionResponseFollowup::new()
+        .ephemeral(true)
+        .content(format!("{}: {}", what, describe_error(err)));
+    if let Some(retry) = retry.filter(|_| is_transient(err)) {
+        followup = followup.components(vec![get_retry_row(retry)]);
+    }
+
+    match cmp.create_followup(ctx.http.clone(), followup).await {
+        Ok(_) => {}
+        Err(err) => {
+            warn!("Failed to create followup: {}", err);
+        }
+    }
+}
+
+fn get_retry_row(retry: &CustomId) -> CreateActionRow {
+    CreateActionRow::Buttons(vec![CreateButton::new(retry.to_string())
+        .label("Retry")
+        .emoji(ReactionType::from('🔁'))
+        .style(ButtonStyle::Primary)])
+}
+
+/// Whether discord answered that the message doesn't exist (anymore)
+fn is_unknown_message(err: &serenity::Error) -> bool {
+    // https://discord.com/developers/docs/topics/opcodes-and-status-codes#json
+    const UNKNOWN_MESSAGE: isize = 10008;
+
+    matches!(
+        err,
+        serenity::Error::Http(serenity::all::HttpError::UnsuccessfulRequest(resp))
+            if resp.error.code == UNKNOWN_MESSAGE
+    )
+}
+
+/// Whether the failure was caused by something that may go away by itself, unlike e.g. a review
+/// that was deleted in the meantime
+fn is_transient(err: &anyhow::Error) -> bool {
+    err.chain().any(|cause| {
+        if let Some(err) = cause.downcast_ref::<reqwest::Error>() {
+            return err.is_timeout()
+                || err.is_connect()
+                || err
+                    .status()
+                    .is_some_and(|status| status.is_server_error() || status.as_u16() == 429);
+        }
+        if let Some(cynic::http::CynicReqwestError::ErrorResponse(status, _)) = cause.downcast_ref()
+        {
+            return status.is_server_error() || status.as_u16() == 429;
+        }
+        if let Some(err) = cause.downcast_ref::<serenity::Error>() {
+            return match err {
+                serenity::Error::Http(serenity::all::HttpError::UnsuccessfulRequest(resp)) => {
+                    resp.status_code.is_server_error() || resp.status_code.as_u16() == 429
+                }
+                serenity::Error::Http(serenity::all::HttpError::Request(_)) => true,
+                _ => false,
+            };
+        }
+        false
+    })
+}
+
+/// Reason for a failure that makes sense to moderators, who can't look into the logs
+fn describe_error(err: &anyhow::Error) -> String {
+    for cause in err.chain() {
+        if let Some(err) = cause.downcast_ref::<GqlError>() {
+            return match err.operation {
+                Operation::Login => "the bot could not log in to the backend".to_string(),
+                _ => shorten(
+                    &format!("the backend refused: {}", err.messages.join("; ")),
+                    MAX_ERROR_LEN,
+                ),
+            };
+        }
+        if let Some(err) = cause.downcast_ref::<reqwest::Error>() {
+            if err.is_timeout() || err.is_connect() {
+                return "the server could not be reached, please try again later".to_string();
+            }
+            if let Some(status) = err.status() {
+                return describe_status(status.as_u16());
+            }
+        }
+        if let Some(cynic::http::CynicReqwestError::ErrorResponse(status, _)) = cause.downcast_ref()
+        {
+            return describe_status(status.as_u16());
+        }
+        if let Some(serenity::Error::Http(serenity::all::HttpError::UnsuccessfulRequest(resp))) =
+            cause.downcast_ref()
+        {
+            return match resp.status_code.as_u16() {
+                403 => "the bot is missing permissions in discord".to_string(),
+                404 => "the message doesn't exist anymore".to_string(),
+                _ => "discord could not be reached, please try again later".to_string(),
+            };
+        }
+    }
+
+    shorten(&format!("{:#}", err), MAX_ERROR_LEN)
+}
+
+fn describe_status(status: u16) -> String {
+    match status {
+        401 | 403 => "the bot is not allowed to do this, its credentials may be wrong".to_string(),
+        404 => "the review or image doesn't exist anymore".to_string(),
+        500.. => "the server has problems, please try again later".to_string(),
+        _ => format!("the server answered with status {}", status),
+    }
+}
+
+/// Sends the message for a new review and remembers it in the store
+async fn post_review(
+    http: &Http,
+    settings: &Settings,
+    image_client: &ImageClient,
+    store: &ReviewStore,
+    review: Review,
+) -> anyhow::Result<()> {
+    let comms = ChannelId::new(settings.discord.comm_channel);
+    let review_id = review.id.0.clone();
+    let location = review.occurrence.location.name.clone();
+
+    let mut attachments = vec![];
+    for image in review.images.iter().take(MAX_EMBEDS) {
+        match get_image_attachment(image_client, &image.id.0).await {
+            Ok(attachment) => attachments.push(attachment),
+            // Better to moderate the rest of the review than not at all
+            Err(err) => warn!(
+                "Could not download image {} of review {}, sending it without: {}",
+                image.id, review_id, err
+            ),
+        }
+    }
+
+    let msg = comms
+        .send_message(http, create_review_embed(settings, review, attachments))
+        .await?;
+
+    // Messages returned by the REST API usually lack the guild, which links to the message need
+    let guild_id = match msg.guild_id {
+        Some(guild_id) => Some(guild_id),
+        None => comms
+            .to_channel(http)
+            .await
+            .ok()
+            .and_then(|channel| channel.guild())
+            .map(|channel| channel.guild_id),
+    };
+
+    // The message is out already, so failing to store it is not worth failing for
+    if let Err(err) = store
+        .insert(
+            &review_id,
+            ReviewRecord {
+                guild_id: guild_id.map(|guild_id| guild_id.get()),
+                location: Some(location),
+                approvals: vec![],
+                claim: None,
+                channel_id: msg.channel_id.get(),
+                message_id: msg.id.get(),
+                state: ModerationState::Pending,
+            },
+        )
+        .await
+    {
+        warn!("Could not store message for review {}: {}", review_id, err);
+    }
+
+    Ok(())
+}
+
+/// Returns the previous state, if the review is known
+///
+/// Moderating stores the new state before updating the backend, as the backend reports our own
+/// changes back to us and we must not mistake them for external ones.
+async fn set_review_state(
+    ctx: &Context,
+    review_id: &str,
+    state: ModerationState,
+) -> Option<ModerationState> {
+    let store = {
+        let guard = ctx.data.read().await;
+        guard
+            .get::<ReviewStore>()
+            .expect("Could not retrieve ReviewStore from global context")
+            .clone()
+    };
+
+    match store.set_state(review_id, state).await {
+        Ok(previous) => previous,
+        Err(err) => {
+            warn!("Could not store state of review {}: {}", review_id, err);
+            None
+        }
+    }
+}
+
+/// Drops the record of an approved or deleted review, so that the store doesn't grow forever
+async fn forget_review(ctx: &Context, review_id: &str) {
+    let store = {
+        let guard = ctx.data.read().await;
+        guard
+            .get::<ReviewStore>()
+            .expect("Could not retrieve ReviewStore from global context")
+            .clone()
+    };
+
+    if let Err(err) = store.remove(review_id).await {
+        warn!(
+            "Could not remove review {} from the store: {}",
+            review_id, err
+        );
+    }
+}
+
+async fn get_review_state(ctx: &Context, review_id: &str) -> Option<ModerationState> {
+    let guard = ctx.data.read().await;
+    guard
+        .get::<ReviewStore>()
+        .expect("Could not retrieve ReviewStore from global context")
+        .get(review_id)
+        .map(|record| record.state)
+}
+
+/// Records the decision in the audit log file and posts it to the audit channel, if there is one
+async fn audit(
+    ctx: &Context,
+    user: &User,
+    action: AuditAction,
+    review_id: &str,
+    dish: Option<String>,
+    prior_state: Option<ModerationState>,
+    reason: Option<String>,
+) {
+    let location = {
+        let guard = ctx.data.read().await;
+        guard
+            .get::<ReviewStore>()
+            .expect("Could not retrieve ReviewStore from global context")
+            .get(review_id)
+            .and_then(|record| record.location)
+    };
+
+    let entry = AuditEntry {
+        timestamp: Utc::now(),
+        sink: "discord".to_string(),
+        user_id: Some(user.id.get()),
+        user_name: user.name.clone(),
+        action,
+        review_id: review_id.to_string(),
+        dish,
+        location,
+        reason,
+        prior_state,
+    };
+
+    let channel = {
+        let guard = ctx.data.read().await;
+        let audit_log = guard
+            .get::<AuditLog>()
+            .expect("Could not retrieve AuditLog from global context");
+        let settings = guard
+            .get::<Settings>()
+            .expect("Could not retrieve settings from global context");
+        if let Err(err) = audit_log.append(&entry) {
+            warn!("Could not write audit log entry {:?}: {}", entry, err);
+        }
+        settings.audit.get_channel()
+    };
+
+    post_audit_entry(&ctx.http, channel, &entry).await;
+}
+
+/// Posts the entry to the audit channel, if one is configured
+pub async fn post_audit_entry(http: &Http, channel: Option<u64>, entry: &AuditEntry) {
+    if let Some(channel) = channel {
+        let msg = CreateMessage::new().content(get_audit_line(entry));
+        match ChannelId::new(channel).send_message(http, msg).await {
+            Ok(_) => {}
+            Err(err) => {
+                warn!("Could not post to audit channel: {}", err);
+            }
+        }
+    }
+}
+
+fn get_audit_line(entry: &AuditEntry) -> String {
+    let dish = entry
+        .dish
+        .as_ref()
+        .map(|dish| format!(" ({})", dish))
+        .unwrap_or_default();
+    let prior_state = entry
+        .prior_state
+        .map(|state| format!(", was {:?}", state).to_lowercase())
+        .unwrap_or_default();
+    let reason = entry
+        .reason
+        .as_ref()
+        .map(|reason| format!(": {}", reason))
+        .unwrap_or_default();
+
+    let user = match entry.user_id {
+        Some(user_id) => format!("<@{}>", user_id),
+        None => format!("{} ({})", entry.user_name, entry.sink),
+    };
+
+    format!(
+        "<t:{}:f> {} {} review `{}`{}{}{}",
+        entry.timestamp.timestamp(),
+        user,
+        entry.action,
+        entry.review_id,
+        dish,
+        prior_state,
+        reason
+    )
+}
+
+fn get_audit_message(review_id: &str, history: &[AuditEntry]) -> String {
+    if history.is_empty() {
+        return format!("Nothing was recorded for review `{}`", review_id);
+    }
+
+    // Discord messages are limited to 2000 characters, the most recent entries matter most
+    let mut lines = vec![];
+    let mut len = 0;
+    for line in history.iter().rev().map(get_audit_line) {
+        if len + line.len() + 1 > 1900 {
+            lines.push("...".to_string());
+            break;
+        }
+        len += line.len() + 1;
+        lines.push(line);
+    }
+    lines.reverse();
+
+    lines.join("\n")
+}
+
+/// Counts the rejection reasons per location
+fn get_report_message(year: i32, month: u32, rejections: &[AuditEntry]) -> String {
+    if rejections.is_empty() {
+        return format!("No reviews were rejected in {}-{:02}", year, month);
+    }
+
+    let mut locations: BTreeMap<&str, BTreeMap<&str, usize>> = BTreeMap::new();
+    for entry in rejections {
+        let location = entry.location.as_deref().unwrap_or("Unknown location");
+        let reason = entry.reason.as_deref().unwrap_or("No reason");
+        *locations
+            .entry(location)
+            .or_default()
+            .entry(reason)
+            .or_default() += 1;
+    }
+
+    let mut message = format!(
+        "{} reviews were rejected in {}-{:02}",
+        rejections.len(),
+        year,
+        month
+    );
+    for (location, reasons) in locations {
+        message += &format!("\n**{}** ({})", location, reasons.values().sum::<usize>());
+
+        let mut reasons = reasons.into_iter().collect::<Vec<_>>();
+        reasons.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+        for (reason, count) in reasons {
+            message += &format!("\n- {}: {}", reason, count);
+        }
+    }
+
+    // Discord messages are limited to 2000 characters
+    if message.chars().count() > 1900 {
+        message = message.chars().take(1900).collect::<String>() + "\n...";
+    }
+    message
+}
+
+async fn publish_event(ctx: &Context, event: ReviewEvent) {
+    let guard = ctx.data.read().await;
+    guard
+        .get::<EventBus>()
+        .expect("Could not retrieve EventBus from global context")
+        .publish(event);
+}
+
+/// The action a button stands for, if pressing it needs a permission
+fn get_action(action: &ComponentAction) -> Option<Action> {
+    match action {
+        // Claiming only makes sense for those who can approve, others can't decide anyway
+        ComponentAction::Approve | ComponentAction::Claim => Some(Action::Approve),
+        ComponentAction::Unapprove | ComponentAction::Reject | ComponentAction::Reason => {
+            Some(Action::Reject)
+        }
+        ComponentAction::Delete
+        | ComponentAction::Remove { .. }
+        | ComponentAction::ConfirmRemove { .. } => Some(Action::Delete),
+        ComponentAction::Rotate { .. } => Some(Action::Rotate),
+        ComponentAction::Edit => Some(Action::Edit),
+        // The controls of the chosen image check the permissions themselves
+        ComponentAction::ChooseImage | ComponentAction::CancelRemove => None,
+    }
+}
+
+/// Whether the member has one of the roles configured for the action
+async fn is_permitted(ctx: &Context, action: Action, member: Option<&Member>) -> bool {
+    let guard = ctx.data.read().await;
+    let settings = guard
+        .get::<Settings>()
+        .expect("Could not retrieve settings from global context");
+
+    let roles = settings.discord.roles.get(action);
+    if roles.is_empty() {
+        return true;
     }
 
-    pub async fn start(&mut self) -> anyhow::Result<()> {
+    // Outside of a guild there are no roles
+    member.is_some_and(|member| member.roles.iter().any(|role| roles.contains(&role.get())))
+}
+
+fn get_refusal(action: Action) -> CreateInteractionResponse {
+    let what = match action {
+        Action::Approve => "approve reviews",
+        Action::Reject => "reject reviews",
+        Action::Delete => "delete reviews or their images",
+        Action::Rotate => "rotate images",
+        Action::Edit => "edit reviews",
+        Action::Recover => "recover reviews",
+    };
+
+    CreateInteractionResponse::Message(
+        CreateInteractionResponseMessage::new()
+            .ephemeral(true)
+            .content(format!("You are not allowed to {}", what)),
+    )
+}
+
+fn get_recover_message(nr: usize, existing: &[(String, String)], unchecked: usize) -> String {
+    let mut content = format!("Recovering {} reviews for you ^-^", nr);
+    if unchecked > 0 {
+        content.push_str(&format!(
+            "\n{} reviews were skipped, as discord could not tell whether their message still \
+             exists. Please try again later",
+            unchecked
+        ));
+    }
+    if existing.is_empty() {
+        return content;
+    }
+
+    content.push_str(&format!(
+        "\n{} reviews already have a message:",
+        existing.len()
+    ));
+    for (i, (dish, link)) in existing.iter().enumerate() {
+        let line = format!("\n- [{}]({})", dish, link);
+        // Stay well below discord's message length limit of 2000 characters
+        if content.len() + line.len() > 1900 {
+            content.push_str(&format!("\n- ...and {} more", existing.len() - i));
+            break;
+        }
+        content.push_str(&line);
+    }
+    content
+}
+
+pub struct Bot {
+    settings: Settings,
+    image_client: Arc<ImageClient>,
+    store: Arc<ReviewStore>,
+    http: Arc<Http>,
+}
+
+impl Bot {
+    pub async fn start(
+        events: EventBus,
+        settings: Settings,
+        store: Arc<ReviewStore>,
+        audit_log: Arc<AuditLog>,
+    ) -> anyhow::Result<Self> {
+        info!("Creating local graphql and image client");
+        let gql_client = Arc::new(MensattGqlClient::new(settings.clone()));
+        let image_client = Arc::new(ImageClient::new(settings.clone()));
+
         let intents = GatewayIntents::empty();
 
         info!("Starting Discord bot...");
-        let mut client = Client::builder(&self.settings.discord.token, intents)
+        let mut client = Client::builder(&settings.discord.token, intents)
             .event_handler(Handler)
-            .await
-            .expect("Failed to create client");
+            .await?;
 
         {
             let mut data = client.data.write().await;
-            data.insert::<MensattGqlClient>(self.gql_client.clone());
-            data.insert::<ImageClient>(self.image_client.clone());
-            data.insert::<Settings>(Arc::new(self.settings.clone()));
+            data.insert::<MensattGqlClient>(gql_client);
+            data.insert::<ImageClient>(image_client.clone());
+            data.insert::<Settings>(Arc::new(settings.clone()));
+            data.insert::<ReviewStore>(store.clone());
+            data.insert::<AuditLog>(audit_log);
+            data.insert::<EventBus>(events);
         }
 
         let http = client.http.clone();
@@ -594,10 +3071,308 @@ impl Bot {
         });
 
         info!("Discord bot started!");
-        info!("Waiting for review events...");
 
-        self.listen_for_gql_events(http).await?;
+        Ok(Bot {
+            settings,
+            image_client,
+            store,
+            http,
+        })
+    }
 
+    async fn mark_approved_externally(&self, review: &Review) -> anyhow::Result<()> {
+        let record = match self.store.get(&review.id.0) {
+            Some(record) => record,
+            None => {
+                info!("Review {} was approved, but has no message", review.id);
+                return Ok(());
+            }
+        };
+
+        // Approvals through our own buttons are reported as well, those are already displayed
+        if record.state == ModerationState::Approved {
+            debug!("Review {} is already marked as approved", review.id);
+            return Ok(());
+        }
+
+        ChannelId::new(record.channel_id)
+            .edit_message(
+                &self.http,
+                MessageId::new(record.message_id),
+                EditMessage::new().components(get_action_row(
+                    &ReviewMessageState::Approve,
+                    &review.id.0,
+                    &review
+                        .images
+                        .iter()
+                        .map(|image| image.id.0.clone())
+                        .collect::<Vec<_>>(),
+                    None,
+                    None,
+                    Quorum::default(),
+                )),
+            )
+            .await?;
+
+        // Approved reviews are done with, keeping them would only grow the store
+        self.store.remove(&review.id.0).await?;
+        info!("Marked review {} as approved externally", review.id);
         Ok(())
     }
+
+    /// Shows a decision made in another sink, approvals are handled by `mark_approved_externally`
+    async fn mark_externally(
+        &self,
+        review_id: &Uuid,
+        state: ModerationState,
+    ) -> anyhow::Result<()> {
+        let message_state = ReviewMessageState::from(state);
+        let record = match self.store.get(&review_id.0) {
+            Some(record) => record,
+            None => {
+                info!(
+                    "Review {} was {}, but has no message",
+                    review_id,
+                    message_state.describe()
+                );
+                return Ok(());
+            }
+        };
+
+        // Changes made through our own buttons are reported as well, those are already displayed
+        if record.state == state {
+            debug!(
+                "Review {} is already marked as {}",
+                review_id,
+                message_state.describe()
+            );
+            return Ok(());
+        }
+
+        let mut msg = ChannelId::new(record.channel_id)
+            .message(&self.http, record.message_id)
+            .await?;
+        let image_ids = get_image_ids(&msg.attachments);
+        let quorum = match state {
+            ModerationState::Deleted => Quorum::default(),
+            _ => get_message_quorum(&self.settings, &self.store, &review_id.0, &msg),
+        };
+        msg.edit(
+            &self.http,
+            EditMessage::new().components(get_action_row(
+                &message_state,
+                &review_id.0,
+                &image_ids,
+                None,
+                None,
+                quorum,
+            )),
+        )
+        .await?;
+
+        if state == ModerationState::Deleted {
+            self.store.remove(&review_id.0).await?;
+        } else {
+            self.store.set_state(&review_id.0, state).await?;
+        }
+        info!(
+            "Marked review {} as {} externally",
+            review_id,
+            message_state.describe()
+        );
+        Ok(())
+    }
+
+    async fn refresh_embed(&self, review: &Review) -> anyhow::Result<()> {
+        let record = match self.store.get(&review.id.0) {
+            Some(record) => record,
+            None => {
+                info!("Review {} was edited, but has no message", review.id);
+                return Ok(());
+            }
+        };
+
+        let mut msg = ChannelId::new(record.channel_id)
+            .message(&self.http, record.message_id)
+            .await?;
+        let filenames = get_filenames(&msg.attachments);
+        msg.edit(
+            &self.http,
+            EditMessage::new().embeds(create_embeds(&self.settings, review, &filenames)),
+        )
+        .await?;
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl NotificationSink for Bot {
+    fn name(&self) -> &str {
+        "discord"
+    }
+
+    async fn deliver_review(&self, review: &Review) -> anyhow::Result<()> {
+        if self.store.get(&review.id.0).is_some() {
+            info!("Review {} already has a message, skipping it", review.id);
+            return Ok(());
+        }
+
+        post_review(
+            &self.http,
+            &self.settings,
+            &self.image_client,
+            &self.store,
+            review.clone(),
+        )
+        .await
+    }
+
+    async fn update_review_state(&self, update: &ReviewUpdate) -> anyhow::Result<()> {
+        match update {
+            ReviewUpdate::Accepted(review) => self.mark_approved_externally(review).await,
+            ReviewUpdate::Edited(review) => self.refresh_embed(review).await,
+            ReviewUpdate::Unapproved(review_id) => {
+                self.mark_externally(review_id, ModerationState::Unapproved)
+                    .await
+            }
+            ReviewUpdate::Rejected(review_id) => {
+                self.mark_externally(review_id, ModerationState::Rejected)
+                    .await
+            }
+            ReviewUpdate::Deleted(review_id) => {
+                self.mark_externally(review_id, ModerationState::Deleted)
+                    .await
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn all_actions() -> Vec<ComponentAction> {
+        let image_id = "0a1b2c3d-4e5f-6a7b-8c9d-0e1f2a3b4c5d".to_string();
+        vec![
+            ComponentAction::Approve,
+            ComponentAction::Unapprove,
+            ComponentAction::Reject,
+            ComponentAction::Reason,
+            ComponentAction::Delete,
+            ComponentAction::Edit,
+            ComponentAction::Claim,
+            ComponentAction::ChooseImage,
+            ComponentAction::Rotate {
+                image_id: image_id.clone(),
+                angle: 90,
+            },
+            ComponentAction::Remove {
+                image_id: image_id.clone(),
+            },
+            ComponentAction::ConfirmRemove { image_id },
+            ComponentAction::CancelRemove,
+        ]
+    }
+
+    #[test]
+    fn apply_all_transitions() {
+        use ReviewMessageState::*;
+
+        // Actions that change the state, each row lists the state after
+        // approve, unapprove, reject, reason and delete
+        let table = [
+            (New, [Some(Approve), None, Some(New), Some(Reject), None]),
+            (Approve, [None, Some(Unapprove), None, None, None]),
+            (
+                Unapprove,
+                [Some(Approve), None, Some(Unapprove), Some(Reject), None],
+            ),
+            (Reject, [Some(Approve), None, None, None, Some(Delete)]),
+            (Delete, [None, None, None, None, None]),
+        ];
+
+        for (state, expected) in table {
+            let actions = all_actions();
+            for (action, expected) in actions.iter().zip(expected) {
+                assert_eq!(
+                    state.apply(action),
+                    expected,
+                    "{:?} with {:?}",
+                    state,
+                    action
+                );
+            }
+            // Everything else leaves the state as is, unless the review is gone
+            for action in &actions[5..] {
+                let expected = if state == Delete { None } else { Some(state) };
+                assert_eq!(
+                    state.apply(action),
+                    expected,
+                    "{:?} with {:?}",
+                    state,
+                    action
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn apply_deleted_refuses_everything() {
+        for action in all_actions() {
+            assert_eq!(
+                ReviewMessageState::Delete.apply(&action),
+                None,
+                "{:?}",
+                action
+            );
+        }
+    }
+
+    #[test]
+    fn apply_rejected_changes_only_by_delete_or_approve() {
+        for action in all_actions() {
+            let next = ReviewMessageState::Reject.apply(&action);
+            match action {
+                ComponentAction::Delete => assert_eq!(next, Some(ReviewMessageState::Delete)),
+                ComponentAction::Approve => assert_eq!(next, Some(ReviewMessageState::Approve)),
+                ComponentAction::Unapprove | ComponentAction::Reject | ComponentAction::Reason => {
+                    assert_eq!(next, None, "{:?}", action)
+                }
+                _ => assert_eq!(next, Some(ReviewMessageState::Reject), "{:?}", action),
+            }
+        }
+    }
+
+    #[test]
+    fn describe_backend_errors() {
+        let err = anyhow::Error::from(GqlError {
+            operation: Operation::DeleteReview,
+            messages: vec!["review not found".to_string()],
+        });
+        assert_eq!(
+            describe_error(&err),
+            "the backend refused: review not found"
+        );
+        assert!(!is_transient(&err));
+
+        let err = anyhow::Error::from(GqlError {
+            operation: Operation::Login,
+            messages: vec!["invalid credentials".to_string()],
+        });
+        assert_eq!(
+            describe_error(&err),
+            "the bot could not log in to the backend"
+        );
+    }
+
+    #[test]
+    fn state_conflicts_are_not_transient() {
+        let err = anyhow::anyhow!("The review was deleted in the meantime");
+        assert!(!is_transient(&err));
+        assert_eq!(
+            describe_error(&err),
+            "The review was deleted in the meantime"
+        );
+    }
 }
@@ -0,0 +1,271 @@
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+// Bumped whenever the format changes, so that buttons of old messages are still understood
+const VERSION: &str = "v1";
+
+/// What pressing a button, choosing from a menu or submitting a modal is supposed to do
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum ComponentAction {
+    Approve,
+    Unapprove,
+    // Asks for a reason, which is then handled by `Reason`
+    Reject,
+    Reason,
+    Delete,
+    Edit,
+    Claim,
+    // Chosen from the menu of images, answered with the controls for that image
+    ChooseImage,
+    // Buttons of messages from before the gallery leave out the image, which is empty then
+    Rotate { image_id: String, angle: i32 },
+    // Asks for confirmation, which is then handled by `ConfirmRemove` or `CancelRemove`
+    Remove { image_id: String },
+    ConfirmRemove { image_id: String },
+    CancelRemove,
+}
+
+/// Custom id of a message component or modal, identifying the review and what to do with it.
+///
+/// Formatted as `v1:<action>:<review>[:<args>...]`, or `v1:retry:<action>:...` for retrying an
+/// action that failed. Review and image ids are UUIDs, which never contain the separator.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct CustomId {
+    pub review_id: String,
+    pub action: ComponentAction,
+    // Messages from before the ids were versioned use the same id for rejecting and unapproving
+    pub legacy: bool,
+    // Sent from an error message instead of the review's message
+    pub retry: bool,
+}
+
+impl CustomId {
+    pub fn new(review_id: &str, action: ComponentAction) -> Self {
+        Self {
+            review_id: review_id.to_string(),
+            action,
+            legacy: false,
+            retry: false,
+        }
+    }
+
+    pub fn for_retry(mut self) -> Self {
+        self.retry = true;
+        self
+    }
+
+    /// Parses ids of messages sent before the ids were versioned, e.g. `rotate_<review>_90`
+    fn parse_legacy(s: &str) -> anyhow::Result<Self> {
+        // Disabled buttons of deleted reviews were given ids like `_____reject_deleted_<review>`
+        if s.starts_with('_') {
+            anyhow::bail!("Custom id {} belongs to a disabled button", s);
+        }
+
+        let parts = s.split('_').collect::<Vec<_>>();
+        let (kind, review_id, args) = match parts.as_slice() {
+            [kind, review_id, args @ ..] => (*kind, *review_id, args),
+            _ => anyhow::bail!("Custom id {} has no review", s),
+        };
+
+        let action = match (kind, args) {
+            // These messages only showed a single image, so it wasn't part of the id
+            ("rotate", [angle]) => ComponentAction::Rotate {
+                image_id: String::new(),
+                angle: angle.parse()?,
+            },
+            ("approve" | "reject" | "delete" | "edit", []) => parse_action(kind, args)?,
+            _ => anyhow::bail!(
+                "Unknown legacy action {} with {} arguments",
+                kind,
+                args.len()
+            ),
+        };
+
+        Ok(Self {
+            review_id: review_id.to_string(),
+            action,
+            legacy: true,
+            retry: false,
+        })
+    }
+}
+
+fn parse_action(kind: &str, args: &[&str]) -> anyhow::Result<ComponentAction> {
+    let action = match (kind, args) {
+        ("approve", []) => ComponentAction::Approve,
+        ("unapprove", []) => ComponentAction::Unapprove,
+        ("reject", []) => ComponentAction::Reject,
+        ("reason", []) => ComponentAction::Reason,
+        ("delete", []) => ComponentAction::Delete,
+        ("edit", []) => ComponentAction::Edit,
+        ("claim", []) => ComponentAction::Claim,
+        ("image", []) => ComponentAction::ChooseImage,
+        ("rotate", [image_id, angle]) => ComponentAction::Rotate {
+            image_id: image_id.to_string(),
+            angle: angle.parse()?,
+        },
+        ("remove", [image_id]) => ComponentAction::Remove {
+            image_id: image_id.to_string(),
+        },
+        ("confirmremove", [image_id]) => ComponentAction::ConfirmRemove {
+            image_id: image_id.to_string(),
+        },
+        ("cancelremove", []) => ComponentAction::CancelRemove,
+        _ => anyhow::bail!("Unknown action {} with {} arguments", kind, args.len()),
+    };
+    Ok(action)
+}
+
+impl FromStr for CustomId {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts = s.split(':').collect::<Vec<_>>();
+        match parts.as_slice() {
+            [VERSION, "retry", kind, review_id, args @ ..] => Ok(Self {
+                review_id: review_id.to_string(),
+                action: parse_action(kind, args)?,
+                legacy: false,
+                retry: true,
+            }),
+            [VERSION, kind, review_id, args @ ..] => Ok(Self {
+                review_id: review_id.to_string(),
+                action: parse_action(kind, args)?,
+                legacy: false,
+                retry: false,
+            }),
+            [version, ..] if version.starts_with('v') && parts.len() > 1 => {
+                anyhow::bail!("Unsupported custom id version in {}", s)
+            }
+            _ => Self::parse_legacy(s),
+        }
+    }
+}
+
+impl Display for CustomId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let (kind, args) = match &self.action {
+            ComponentAction::Approve => ("approve", vec![]),
+            ComponentAction::Unapprove => ("unapprove", vec![]),
+            ComponentAction::Reject => ("reject", vec![]),
+            ComponentAction::Reason => ("reason", vec![]),
+            ComponentAction::Delete => ("delete", vec![]),
+            ComponentAction::Edit => ("edit", vec![]),
+            ComponentAction::Claim => ("claim", vec![]),
+            ComponentAction::ChooseImage => ("image", vec![]),
+            ComponentAction::Rotate { image_id, angle } => {
+                ("rotate", vec![image_id.clone(), angle.to_string()])
+            }
+            ComponentAction::Remove { image_id } => ("remove", vec![image_id.clone()]),
+            ComponentAction::ConfirmRemove { image_id } => {
+                ("confirmremove", vec![image_id.clone()])
+            }
+            ComponentAction::CancelRemove => ("cancelremove", vec![]),
+        };
+
+        write!(f, "{}:", VERSION)?;
+        if self.retry {
+            write!(f, "retry:")?;
+        }
+        write!(f, "{}:{}", kind, self.review_id)?;
+        for arg in args {
+            write!(f, ":{}", arg)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const REVIEW: &str = "4f9c1d2e-8a3b-4c5d-9e6f-7a8b9c0d1e2f";
+    const IMAGE: &str = "0a1b2c3d-4e5f-6a7b-8c9d-0e1f2a3b4c5d";
+
+    fn all_actions() -> Vec<ComponentAction> {
+        vec![
+            ComponentAction::Approve,
+            ComponentAction::Unapprove,
+            ComponentAction::Reject,
+            ComponentAction::Reason,
+            ComponentAction::Delete,
+            ComponentAction::Edit,
+            ComponentAction::Claim,
+            ComponentAction::ChooseImage,
+            ComponentAction::Rotate {
+                image_id: IMAGE.to_string(),
+                angle: 270,
+            },
+            ComponentAction::Remove {
+                image_id: IMAGE.to_string(),
+            },
+            ComponentAction::ConfirmRemove {
+                image_id: IMAGE.to_string(),
+            },
+            ComponentAction::CancelRemove,
+        ]
+    }
+
+    #[test]
+    fn round_trip() {
+        for action in all_actions() {
+            let id = CustomId::new(REVIEW, action.clone());
+            assert_eq!(id.to_string().parse::<CustomId>().unwrap(), id);
+
+            let retry = id.for_retry();
+            assert!(retry.to_string().starts_with("v1:retry:"));
+            assert_eq!(retry.to_string().parse::<CustomId>().unwrap(), retry);
+        }
+    }
+
+    #[test]
+    fn parse_legacy() {
+        let id = format!("rotate_{}_90", REVIEW).parse::<CustomId>().unwrap();
+        assert_eq!(id.review_id, REVIEW);
+        assert_eq!(
+            id.action,
+            ComponentAction::Rotate {
+                image_id: String::new(),
+                angle: 90
+            }
+        );
+        assert!(id.legacy);
+        assert!(!id.retry);
+
+        // Images only became part of the id with the versioned format
+        assert!(format!("rotate_{}_{}_90", REVIEW, IMAGE)
+            .parse::<CustomId>()
+            .is_err());
+        assert!(format!("remove_{}_{}", REVIEW, IMAGE)
+            .parse::<CustomId>()
+            .is_err());
+
+        let id = format!("reject_{}", REVIEW).parse::<CustomId>().unwrap();
+        assert_eq!(id.review_id, REVIEW);
+        assert_eq!(id.action, ComponentAction::Reject);
+        assert!(id.legacy);
+
+        assert!(format!("_____reject_deleted_{}", REVIEW)
+            .parse::<CustomId>()
+            .is_err());
+        assert!(format!("_____approve_deleted_{}", REVIEW)
+            .parse::<CustomId>()
+            .is_err());
+    }
+
+    #[test]
+    fn parse_invalid() {
+        let err = format!("v2:approve:{}", REVIEW)
+            .parse::<CustomId>()
+            .unwrap_err();
+        assert!(err.to_string().contains("Unsupported custom id version"));
+
+        assert!(format!("v1:approve:{}:extra", REVIEW)
+            .parse::<CustomId>()
+            .is_err());
+        assert!(format!("v1:rotate:{}:{}:sideways", REVIEW, IMAGE)
+            .parse::<CustomId>()
+            .is_err());
+        assert!("approve".parse::<CustomId>().is_err());
+    }
+}
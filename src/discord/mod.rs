@@ -1 +1,2 @@
 pub mod bot;
+mod custom_id;
@@ -0,0 +1,43 @@
+use crate::gql::{Review, Uuid};
+use log::debug;
+use tokio::sync::broadcast;
+
+#[derive(Debug, Clone)]
+pub enum ReviewEvent {
+    Created(Review),
+    Accepted(Review),
+    Updated(Review),
+    // Only published by us, the backend doesn't report these
+    Unapproved(Uuid),
+    Rejected(Uuid),
+    Deleted(Uuid),
+    ListenerConnected,
+    ListenerDisconnected,
+}
+
+/// Distributes review events to any number of independent consumers.
+///
+/// Every consumer gets its own receiver through [`EventBus::subscribe`] and sees all events
+/// published after subscribing.
+#[derive(Clone)]
+pub struct EventBus {
+    tx: broadcast::Sender<ReviewEvent>,
+}
+
+impl EventBus {
+    pub fn new(capacity: usize) -> Self {
+        let (tx, _) = broadcast::channel(capacity);
+        Self { tx }
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<ReviewEvent> {
+        self.tx.subscribe()
+    }
+
+    pub fn publish(&self, event: ReviewEvent) {
+        // Only fails if there are no receivers, in which case nobody cares about the event anyway
+        if let Err(err) = self.tx.send(event) {
+            debug!("Nobody is listening for review event: {:?}", err.0);
+        }
+    }
+}
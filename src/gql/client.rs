@@ -2,10 +2,14 @@ use std::sync::{Arc, RwLock};
 use std::time::{SystemTime, UNIX_EPOCH};
 
 use crate::gql::mutations::{
-    DeleteReviewMutation, DeleteReviewMutationVariables, LoginMutation, LoginMutationVariables,
-    UpdateReviewMutation, UpdateReviewMutationVariables,
+    DeleteReviewMutation, DeleteReviewMutationVariables, EditReviewMutation,
+    EditReviewMutationVariables, LoginMutation, LoginMutationVariables,
+    RemoveImagesFromReviewMutation, RemoveImagesFromReviewMutationVariables, UpdateReviewMutation,
+    UpdateReviewMutationVariables,
+};
+use crate::gql::queries::{
+    RetrieveReviewApprovalsQuery, RetrieveReviewsQuery, RetrieveReviewsQueryVariables,
 };
-use crate::gql::queries::{RetrieveReviewsQuery, RetrieveReviewsQueryVariables};
 use crate::gql::{Review, Uuid};
 use crate::settings::Settings;
 use cynic::http::ReqwestExt;
@@ -13,6 +17,7 @@ use cynic::MutationBuilder;
 use cynic::QueryBuilder;
 use log::{debug, info};
 use serde::Deserialize;
+use std::fmt::{Display, Formatter};
 
 #[derive(Debug, Clone, Deserialize)]
 // NOTE: There are other fields as well, but we currently don't need them
@@ -25,6 +30,57 @@ struct JwtState {
     expires_at: u64, // Token expiration timestamp (in s since UNIX epoch
 }
 
+/// Requests we make to the backend
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Operation {
+    Login,
+    RetrieveReviews,
+    RetrieveReviewApprovals,
+    UpdateReview,
+    EditReview,
+    RemoveImagesFromReview,
+    DeleteReview,
+}
+
+impl Display for Operation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let operation = match self {
+            Operation::Login => "Login",
+            Operation::RetrieveReviews => "Retrieve reviews",
+            Operation::RetrieveReviewApprovals => "Retrieve review approvals",
+            Operation::UpdateReview => "Update review",
+            Operation::EditReview => "Edit review",
+            Operation::RemoveImagesFromReview => "Remove images from review",
+            Operation::DeleteReview => "Delete review",
+        };
+        write!(f, "{}", operation)
+    }
+}
+
+/// The backend answered a request with errors instead of data
+#[derive(Debug)]
+pub struct GqlError {
+    pub operation: Operation,
+    pub messages: Vec<String>,
+}
+
+impl GqlError {
+    fn new(operation: Operation, errors: Vec<cynic::GraphQlError>) -> Self {
+        Self {
+            operation,
+            messages: errors.into_iter().map(|error| error.message).collect(),
+        }
+    }
+}
+
+impl Display for GqlError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} failed: {}", self.operation, self.messages.join("; "))
+    }
+}
+
+impl std::error::Error for GqlError {}
+
 pub struct MensattGqlClient {
     settings: Settings,
     http_client: reqwest::Client,
@@ -88,8 +144,8 @@ impl MensattGqlClient {
 
         debug!("Login response: {:#?}", response);
 
-        if response.errors.is_some() {
-            return Err(anyhow::anyhow!("Login failed: {:#?}", response.errors));
+        if let Some(errors) = response.errors {
+            return Err(GqlError::new(Operation::Login, errors).into());
         }
 
         let jwt = response
@@ -120,11 +176,8 @@ impl MensattGqlClient {
 
         debug!("Retrieve reviews response: {:#?}", response);
 
-        if response.errors.is_some() {
-            return Err(anyhow::anyhow!(
-                "Retrieve reviews failed: {:#?}",
-                response.errors
-            ));
+        if let Some(errors) = response.errors {
+            return Err(GqlError::new(Operation::RetrieveReviews, errors).into());
         }
 
         if let Some(data) = response.data {
@@ -134,6 +187,51 @@ impl MensattGqlClient {
         Err(anyhow::anyhow!("Got no data when retrieving reviews"))
     }
 
+    /// Whether the review is approved right now, `None` if it doesn't exist (anymore)
+    ///
+    /// Only the reviews with the `expected` approval are retrieved first, so that usually a single
+    /// request is needed.
+    pub async fn is_review_approved(
+        &self,
+        id: &Uuid,
+        expected: bool,
+    ) -> anyhow::Result<Option<bool>> {
+        for approved in [expected, !expected] {
+            if self.has_review(id, approved).await? {
+                return Ok(Some(approved));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Whether the review is among those with the given approval
+    async fn has_review(&self, id: &Uuid, approved: bool) -> anyhow::Result<bool> {
+        // There is no query for a single review, but only the id and approval are retrieved
+        let get_query =
+            RetrieveReviewApprovalsQuery::build(RetrieveReviewsQueryVariables { approved });
+
+        let response = self
+            .http_client
+            .post(self.settings.graphql.https_url.as_str())
+            .bearer_auth(self.get_jwt().await?)
+            .run_graphql(get_query)
+            .await?;
+
+        debug!("Retrieve review approvals response: {:#?}", response);
+
+        if let Some(errors) = response.errors {
+            return Err(GqlError::new(Operation::RetrieveReviewApprovals, errors).into());
+        }
+
+        if let Some(data) = response.data {
+            return Ok(data.reviews.iter().any(|review| review.id.0 == id.0));
+        }
+
+        Err(anyhow::anyhow!(
+            "Got no data when retrieving review approvals"
+        ))
+    }
+
     pub async fn update_review(&self, id: Uuid, approved: bool) -> anyhow::Result<()> {
         let update_mutation =
             UpdateReviewMutation::build(UpdateReviewMutationVariables { id, approved });
@@ -147,11 +245,8 @@ impl MensattGqlClient {
 
         debug!("Update review response: {:#?}", response);
 
-        if response.errors.is_some() {
-            return Err(anyhow::anyhow!(
-                "Update review failed: {:#?}",
-                response.errors
-            ));
+        if let Some(errors) = response.errors {
+            return Err(GqlError::new(Operation::UpdateReview, errors).into());
         }
 
         if let Some(data) = response.data {
@@ -164,6 +259,82 @@ impl MensattGqlClient {
         Ok(())
     }
 
+    /// Updates the user-visible content of a review. Fields that are `None` are left untouched.
+    pub async fn edit_review(
+        &self,
+        id: Uuid,
+        display_name: Option<String>,
+        text: Option<String>,
+        stars: Option<i32>,
+    ) -> anyhow::Result<Review> {
+        let edit_mutation = EditReviewMutation::build(EditReviewMutationVariables {
+            id: id.clone(),
+            display_name,
+            text,
+            stars,
+        });
+
+        let response = self
+            .http_client
+            .post(self.settings.graphql.https_url.as_str())
+            .bearer_auth(self.get_jwt().await?)
+            .run_graphql(edit_mutation)
+            .await?;
+
+        debug!("Edit review response: {:#?}", response);
+
+        if let Some(errors) = response.errors {
+            return Err(GqlError::new(Operation::EditReview, errors).into());
+        }
+
+        let review = response
+            .data
+            .ok_or_else(|| anyhow::anyhow!("Editing review '{}' failed: No Response Data", id))?
+            .update_review;
+
+        info!("Successfully edited review with id {}", id);
+        Ok(review)
+    }
+
+    /// Detaches the images from the review and returns what is left of it
+    pub async fn remove_images_from_review(
+        &self,
+        id: Uuid,
+        images: Vec<Uuid>,
+    ) -> anyhow::Result<Review> {
+        let remove_mutation =
+            RemoveImagesFromReviewMutation::build(RemoveImagesFromReviewMutationVariables {
+                review: id.clone(),
+                images,
+            });
+
+        let response = self
+            .http_client
+            .post(self.settings.graphql.https_url.as_str())
+            .bearer_auth(self.get_jwt().await?)
+            .run_graphql(remove_mutation)
+            .await?;
+
+        debug!("Remove images from review response: {:#?}", response);
+
+        if let Some(errors) = response.errors {
+            return Err(GqlError::new(Operation::RemoveImagesFromReview, errors).into());
+        }
+
+        let review = response
+            .data
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "Removing images from review '{}' failed: No Response Data",
+                    id
+                )
+            })?
+            .remove_images_from_review;
+
+        info!("Successfully removed images from review with id {}", id);
+        Ok(review)
+    }
+
     pub async fn delete_review(&self, id: Uuid) -> anyhow::Result<()> {
         let delete_mutation =
             DeleteReviewMutation::build(DeleteReviewMutationVariables { id: id.clone() });
@@ -177,11 +348,8 @@ impl MensattGqlClient {
 
         debug!("Delete review response: {:#?}", response);
 
-        if response.errors.is_some() {
-            return Err(anyhow::anyhow!(
-                "Delete review failed: {:#?}",
-                response.errors
-            ));
+        if let Some(errors) = response.errors {
+            return Err(GqlError::new(Operation::DeleteReview, errors).into());
         }
 
         let was_deleted = response
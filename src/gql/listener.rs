@@ -1,38 +1,80 @@
-use crate::gql::subscriptions::CreateReviewSubscription;
+use crate::events::{EventBus, ReviewEvent};
+use crate::gql::client::MensattGqlClient;
+use crate::gql::subscriptions::{AcceptReviewSubscription, CreateReviewSubscription};
 use crate::gql::Review;
-use crate::settings::Settings;
+use crate::settings::{Reconnect, Settings};
+use chrono::{DateTime, Utc};
 use cynic::{GraphQlResponse, SubscriptionBuilder};
 use futures::StreamExt;
 use graphql_ws_client::Client;
 use log::{debug, error, info, warn};
-use std::time::Duration;
+use std::fmt::Debug;
+use std::future::IntoFuture;
+use std::time::{Duration, Instant};
 use tokio_tungstenite::tungstenite::client::IntoClientRequest;
 use tokio_tungstenite::tungstenite::http::HeaderValue;
 
+#[derive(Debug)]
+enum SubscriptionMessage {
+    Created(GraphQlResponse<CreateReviewSubscription>),
+    Accepted(GraphQlResponse<AcceptReviewSubscription>),
+}
+
 pub struct ReviewListener {
     settings: Settings,
-    tx: tokio::sync::mpsc::Sender<Review>,
+    events: EventBus,
+    gql_client: MensattGqlClient,
+    // Creation time of the newest review we know of, used to find reviews missed while disconnected
+    last_seen: DateTime<Utc>,
+    // Number of the current reconnect attempt, 0 while connected or before the first failure
+    attempt: u32,
+    connected_at: Option<Instant>,
 }
 
 impl ReviewListener {
-    pub fn new(settings: Settings, tx: tokio::sync::mpsc::Sender<Review>) -> Self {
-        Self { settings, tx }
+    pub fn new(settings: Settings, events: EventBus) -> Self {
+        let gql_client = MensattGqlClient::new(settings.clone());
+        Self {
+            settings,
+            events,
+            gql_client,
+            last_seen: Utc::now(),
+            attempt: 0,
+            connected_at: None,
+        }
     }
 
-    pub async fn continuous_listen(&self) -> ! {
+    pub async fn continuous_listen(&mut self) -> ! {
+        let reconnect = self.settings.graphql.reconnect.clone();
+
         loop {
-            match self.listen().await {
-                Ok(_) => {}
-                Err(err) => {
-                    error!("Error while listening for review creation: {}", err);
-                    warn!("Trying again in 60 seconds");
-                    tokio::time::sleep(Duration::from_secs(60)).await;
+            let result = self.listen().await;
+
+            match &result {
+                Ok(_) => warn!("Subscriptions to review events were closed"),
+                Err(err) if self.attempt > 0 && self.connected_at.is_none() => {
+                    error!("Reconnect attempt {} failed: {}", self.attempt, err)
                 }
+                Err(err) => error!("Error while listening for review events: {}", err),
             }
+
+            let connected_for = self.connected_at.take().map(|connected_at| {
+                self.events.publish(ReviewEvent::ListenerDisconnected);
+                connected_at.elapsed()
+            });
+
+            self.attempt = get_next_attempt(&reconnect, self.attempt, connected_for);
+            let backoff = get_backoff(&reconnect, self.attempt);
+            warn!(
+                "Reconnect attempt {} in {:.1} seconds",
+                self.attempt,
+                backoff.as_secs_f64()
+            );
+            tokio::time::sleep(backoff).await;
         }
     }
 
-    async fn listen(&self) -> anyhow::Result<()> {
+    async fn listen(&mut self) -> anyhow::Result<()> {
         let mut req = self.settings.graphql.ws_url.clone().into_client_request()?;
         req.headers_mut().insert(
             "Sec-WebSocket-Protocol",
@@ -47,14 +89,42 @@ impl ReviewListener {
         let (ws_stream, resp) = tokio_tungstenite::connect_async(req).await?;
         debug!("Websocket connection established: {:?}", resp);
 
-        let gql = Client::build(ws_stream)
-            .keep_alive_interval(Duration::from_secs(30))
-            .keep_alive_retries(10)
-            .subscription_buffer_size(32);
+        let reconnect = &self.settings.graphql.reconnect;
+        let (gql, actor) = Client::build(ws_stream)
+            .keep_alive_interval(Duration::from_secs(reconnect.keep_alive_interval_secs))
+            .keep_alive_retries(reconnect.keep_alive_retries)
+            .subscription_buffer_size(reconnect.subscription_buffer_size)
+            .await?;
 
-        let mut subscription = gql.subscribe(CreateReviewSubscription::build(())).await?;
+        // The actor drives the connection and shuts it down by itself once the client and all
+        // subscriptions are dropped
+        tokio::spawn(actor.into_future());
 
-        info!("Successfully subscribed to review creation");
+        // Both subscriptions share the same websocket connection
+        let created = gql
+            .subscribe(CreateReviewSubscription::build(()))
+            .await?
+            .map(|msg| msg.map(SubscriptionMessage::Created));
+        let accepted = gql
+            .subscribe(AcceptReviewSubscription::build(()))
+            .await?
+            .map(|msg| msg.map(SubscriptionMessage::Accepted));
+        let mut subscription = futures::stream::select(created, accepted);
+
+        info!("Successfully subscribed to review creation and acceptance");
+        if self.attempt > 0 {
+            info!("Reconnected after {} attempts", self.attempt);
+        }
+        self.connected_at = Some(Instant::now());
+        self.events.publish(ReviewEvent::ListenerConnected);
+
+        // Anything created while we were not subscribed would otherwise be lost
+        if let Err(err) = self.fill_gap().await {
+            warn!(
+                "Could not retrieve reviews missed while disconnected: {}",
+                err
+            );
+        }
 
         while let Some(msg) = subscription.next().await {
             match msg {
@@ -71,34 +141,252 @@ impl ReviewListener {
         Ok(())
     }
 
+    async fn fill_gap(&mut self) -> anyhow::Result<()> {
+        let missed = get_missed(
+            self.gql_client.get_unapproved_reviews().await?,
+            self.last_seen,
+        );
+
+        if !missed.is_empty() {
+            info!(
+                "Found {} reviews created while disconnected, sending them now",
+                missed.len()
+            );
+        }
+
+        for (created_at, review) in missed {
+            self.events.publish(ReviewEvent::Created(review));
+            self.last_seen = created_at;
+        }
+
+        Ok(())
+    }
+
     async fn handle_subscription_message(
-        &self,
-        msg: GraphQlResponse<CreateReviewSubscription>,
+        &mut self,
+        msg: SubscriptionMessage,
     ) -> anyhow::Result<()> {
         debug!("Received message from subscription: {:?}", msg);
 
-        let data = match msg.data {
-            Some(data) => data,
-            None => {
-                if let Some(err) = msg.errors {
-                    warn!(
-                        "Error while receiving message from subscription: {:#?}",
-                        err
-                    );
-                }
-                return Ok(());
+        let event = match msg {
+            SubscriptionMessage::Created(msg) => {
+                get_data(msg).map(|data| data.review_created.map(ReviewEvent::Created))
+            }
+            SubscriptionMessage::Accepted(msg) => {
+                get_data(msg).map(|data| data.review_accepted.map(ReviewEvent::Accepted))
             }
         };
 
-        if let Some(review) = data.review_created {
-            self.tx.send(review).await?;
-        } else {
+        match event {
+            // Errors were already logged while extracting the data
+            None => {}
+            Some(None) => {
+                warn!("Received message from subscription without review");
+            }
+            Some(Some(event)) => {
+                if let ReviewEvent::Created(review) = &event {
+                    match review.created_at.parse() {
+                        Ok(created_at) => self.last_seen = self.last_seen.max(created_at),
+                        Err(err) => warn!(
+                            "Could not parse creation time of review {}: {}",
+                            review.id, err
+                        ),
+                    }
+                }
+                self.events.publish(event);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn get_data<T: Debug>(msg: GraphQlResponse<T>) -> Option<T> {
+    if msg.data.is_none() {
+        if let Some(err) = msg.errors {
             warn!(
-                "Received message from subscription with unknown data: {:#?}",
-                data
+                "Error while receiving message from subscription: {:#?}",
+                err
             );
         }
+    }
+    msg.data
+}
+
+/// Returns the reviews created after `last_seen`, oldest first
+fn get_missed(reviews: Vec<Review>, last_seen: DateTime<Utc>) -> Vec<(DateTime<Utc>, Review)> {
+    let mut missed = vec![];
+    for review in reviews {
+        // One broken review shouldn't keep the others from being sent
+        let created_at = match review.created_at.parse() {
+            Ok(created_at) => created_at,
+            Err(err) => {
+                warn!(
+                    "Could not parse creation time of review {}, skipping it: {}",
+                    review.id, err
+                );
+                continue;
+            }
+        };
+        if created_at > last_seen {
+            missed.push((created_at, review));
+        }
+    }
+    missed.sort_by_key(|(created_at, _)| *created_at);
+    missed
+}
 
-        Ok(())
+/// Counts the reconnect attempt, `connected_for` is how long the last connection lasted if there
+/// was one
+fn get_next_attempt(reconnect: &Reconnect, attempt: u32, connected_for: Option<Duration>) -> u32 {
+    // Only start from the initial backoff again if the last connection was not flaky
+    match connected_for {
+        Some(connected_for)
+            if connected_for >= Duration::from_secs(reconnect.stable_after_secs) =>
+        {
+            1
+        }
+        _ => attempt + 1,
+    }
+}
+
+/// Exponential backoff for the given reconnect attempt (starting at 1), randomly varied by the
+/// configured jitter so that multiple instances don't all reconnect at the same time
+fn get_backoff(reconnect: &Reconnect, attempt: u32) -> Duration {
+    // Casting large attempts would wrap around to a negative exponent
+    let exponent = i32::try_from(attempt.saturating_sub(1)).unwrap_or(i32::MAX);
+    let backoff = (reconnect.initial_backoff_secs as f64 * 2f64.powi(exponent))
+        .min(reconnect.max_backoff_secs as f64);
+    let jitter = backoff * reconnect.jitter * rand::random_range(-1.0..=1.0);
+    Duration::from_secs_f64((backoff + jitter).max(0.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gql::{Dish, Location, Occurrence, Timestamp, Uuid};
+
+    fn get_review(id: &str, created_at: &str) -> Review {
+        Review {
+            id: Uuid(id.to_string()),
+            occurrence: Occurrence {
+                id: Uuid("occurrence".to_string()),
+                dish: Dish {
+                    name_de: "Pommes".to_string(),
+                },
+                location: Location {
+                    name: "Mensa".to_string(),
+                },
+            },
+            display_name: None,
+            stars: 5,
+            text: None,
+            created_at: Timestamp(created_at.to_string()),
+            images: vec![],
+        }
+    }
+
+    fn get_ids(missed: &[(DateTime<Utc>, Review)]) -> Vec<&str> {
+        missed
+            .iter()
+            .map(|(_, review)| review.id.0.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_the_maximum() {
+        let reconnect = Reconnect {
+            jitter: 0.0,
+            ..Reconnect::default()
+        };
+        let backoffs = (1..=8)
+            .map(|attempt| get_backoff(&reconnect, attempt).as_secs())
+            .collect::<Vec<_>>();
+        assert_eq!(backoffs, vec![5, 10, 20, 40, 80, 160, 300, 300]);
+        // Huge attempts must not overflow
+        assert_eq!(get_backoff(&reconnect, u32::MAX).as_secs(), 300);
+    }
+
+    #[test]
+    fn backoff_stays_within_jitter() {
+        let reconnect = Reconnect::default();
+        for _ in 0..1000 {
+            let backoff = get_backoff(&reconnect, 2).as_secs_f64();
+            assert!((8.0..=12.0).contains(&backoff), "{}", backoff);
+            // The jitter applies to the capped backoff
+            let backoff = get_backoff(&reconnect, 20).as_secs_f64();
+            assert!((240.0..=360.0).contains(&backoff), "{}", backoff);
+        }
+    }
+
+    #[test]
+    fn backoff_is_never_negative() {
+        let reconnect = Reconnect {
+            jitter: 2.0,
+            ..Reconnect::default()
+        };
+        for _ in 0..1000 {
+            assert!(get_backoff(&reconnect, 1) <= Duration::from_secs(15));
+        }
+    }
+
+    #[test]
+    fn attempts_reset_after_stable_connection() {
+        let reconnect = Reconnect::default();
+        let stable = Duration::from_secs(reconnect.stable_after_secs);
+        assert_eq!(get_next_attempt(&reconnect, 4, Some(stable)), 1);
+        assert_eq!(
+            get_next_attempt(&reconnect, 4, Some(stable + Duration::from_secs(1))),
+            1
+        );
+    }
+
+    #[test]
+    fn attempts_keep_counting_while_flaky() {
+        let reconnect = Reconnect::default();
+        let flaky = Duration::from_secs(reconnect.stable_after_secs - 1);
+        assert_eq!(get_next_attempt(&reconnect, 4, Some(flaky)), 5);
+        // Failing to connect at all
+        assert_eq!(get_next_attempt(&reconnect, 4, None), 5);
+        assert_eq!(get_next_attempt(&reconnect, 0, None), 1);
+    }
+
+    #[test]
+    fn missed_reviews_are_newer_than_last_seen() {
+        let last_seen = "2026-10-14T12:00:00Z".parse().unwrap();
+        let reviews = vec![
+            get_review("before", "2026-10-14T11:59:59Z"),
+            // Was already sent when it was seen
+            get_review("seen", "2026-10-14T12:00:00Z"),
+            get_review("after", "2026-10-14T12:00:01Z"),
+        ];
+        assert_eq!(get_ids(&get_missed(reviews, last_seen)), vec!["after"]);
+    }
+
+    #[test]
+    fn missed_reviews_are_sorted_by_creation() {
+        let last_seen = "2026-10-14T12:00:00Z".parse().unwrap();
+        let reviews = vec![
+            get_review("third", "2026-10-14T14:00:00Z"),
+            // Offsets are compared by the actual point in time
+            get_review("first", "2026-10-14T14:30:00+02:00"),
+            get_review("second", "2026-10-14T13:00:00Z"),
+        ];
+        let missed = get_missed(reviews, last_seen);
+        assert_eq!(get_ids(&missed), vec!["first", "second", "third"]);
+        assert_eq!(
+            missed.last().map(|(created_at, _)| *created_at),
+            "2026-10-14T14:00:00Z".parse().ok()
+        );
+    }
+
+    #[test]
+    fn missed_reviews_skip_broken_timestamps() {
+        let last_seen = "2026-10-14T12:00:00Z".parse().unwrap();
+        let reviews = vec![
+            get_review("broken", "yesterday"),
+            get_review("valid", "2026-10-14T13:00:00Z"),
+        ];
+        assert_eq!(get_ids(&get_missed(reviews, last_seen)), vec!["valid"]);
     }
 }
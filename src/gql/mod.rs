@@ -1,3 +1,4 @@
+use chrono::{DateTime, Utc};
 use std::fmt::{Display, Formatter};
 
 pub mod client;
@@ -13,7 +14,7 @@ mod schema {}
 #[cynic(graphql_type = "UUID")]
 pub struct Uuid(pub String);
 
-#[derive(cynic::QueryFragment, Debug)]
+#[derive(cynic::QueryFragment, Debug, Clone)]
 pub struct Review {
     pub id: Uuid,
     pub occurrence: Occurrence,
@@ -24,18 +25,24 @@ pub struct Review {
     pub images: Vec<Image>,
 }
 
-#[derive(cynic::QueryFragment, Debug)]
+#[derive(cynic::QueryFragment, Debug, Clone)]
 pub struct Occurrence {
     pub id: Uuid,
     pub dish: Dish,
+    pub location: Location,
 }
 
-#[derive(cynic::QueryFragment, Debug)]
+#[derive(cynic::QueryFragment, Debug, Clone)]
+pub struct Location {
+    pub name: String,
+}
+
+#[derive(cynic::QueryFragment, Debug, Clone)]
 pub struct Image {
     pub id: Uuid,
 }
 
-#[derive(cynic::QueryFragment, Debug)]
+#[derive(cynic::QueryFragment, Debug, Clone)]
 pub struct Dish {
     pub name_de: String,
 }
@@ -43,6 +50,12 @@ pub struct Dish {
 #[derive(cynic::Scalar, Debug, Clone)]
 pub struct Timestamp(pub String);
 
+impl Timestamp {
+    pub fn parse(&self) -> anyhow::Result<DateTime<Utc>> {
+        Ok(DateTime::parse_from_rfc3339(&self.0)?.with_timezone(&Utc))
+    }
+}
+
 // TODO: Is there a better way for this?
 impl Display for Uuid {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
@@ -42,3 +42,37 @@ pub struct DeleteReviewMutation {
     #[arguments(input: { id: $id })]
     pub delete_review: bool,
 }
+
+#[derive(cynic::QueryVariables, Debug)]
+pub struct EditReviewMutationVariables {
+    pub id: Uuid,
+    #[cynic(skip_serializing_if = "Option::is_none")]
+    pub display_name: Option<String>,
+    #[cynic(skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    #[cynic(skip_serializing_if = "Option::is_none")]
+    pub stars: Option<i32>,
+}
+
+#[derive(cynic::QueryFragment, Debug)]
+#[cynic(graphql_type = "Mutation", variables = "EditReviewMutationVariables")]
+pub struct EditReviewMutation {
+    #[arguments(input: { id: $id, displayName: $display_name, text: $text, stars: $stars })]
+    pub update_review: crate::gql::Review,
+}
+
+#[derive(cynic::QueryVariables, Debug)]
+pub struct RemoveImagesFromReviewMutationVariables {
+    pub review: Uuid,
+    pub images: Vec<Uuid>,
+}
+
+#[derive(cynic::QueryFragment, Debug)]
+#[cynic(
+    graphql_type = "Mutation",
+    variables = "RemoveImagesFromReviewMutationVariables"
+)]
+pub struct RemoveImagesFromReviewMutation {
+    #[arguments(input: { review: $review, images: $images })]
+    pub remove_images_from_review: crate::gql::Review,
+}
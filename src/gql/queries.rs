@@ -1,4 +1,4 @@
-use crate::gql::{schema, Review};
+use crate::gql::{schema, Review, Uuid};
 #[derive(cynic::QueryVariables, Debug)]
 pub struct RetrieveReviewsQueryVariables {
     pub approved: bool,
@@ -10,3 +10,16 @@ pub struct RetrieveReviewsQuery {
     #[arguments(filter: { approved: $approved })]
     pub reviews: Vec<Review>,
 }
+
+#[derive(cynic::QueryFragment, Debug)]
+#[cynic(graphql_type = "Review")]
+pub struct ReviewApproval {
+    pub id: Uuid,
+}
+
+#[derive(cynic::QueryFragment, Debug)]
+#[cynic(graphql_type = "Query", variables = "RetrieveReviewsQueryVariables")]
+pub struct RetrieveReviewApprovalsQuery {
+    #[arguments(filter: { approved: $approved })]
+    pub reviews: Vec<ReviewApproval>,
+}
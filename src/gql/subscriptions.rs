@@ -5,3 +5,9 @@ use crate::gql::{schema, Review};
 pub struct CreateReviewSubscription {
     pub review_created: Option<Review>,
 }
+
+#[derive(cynic::QueryFragment, Debug)]
+#[cynic(graphql_type = "Subscription")]
+pub struct AcceptReviewSubscription {
+    pub review_accepted: Option<Review>,
+}
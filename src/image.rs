@@ -30,7 +30,28 @@ impl ImageClient {
                 self.settings.image.rotate_url, id, angle
             ))
             .send()
-            .await?;
+            .await?
+            .error_for_status()?;
         Ok(())
     }
+
+    /// Returns the image data together with its content type
+    pub async fn download_image(&self, id: &str) -> anyhow::Result<(Vec<u8>, String)> {
+        let resp = self
+            .client
+            // The key is sent as header only, as urls end up in error messages
+            .get(format!("{}{}", self.settings.image.image_url, id))
+            .send()
+            .await?
+            .error_for_status()?;
+
+        let content_type = resp
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or("image/jpeg")
+            .to_string();
+
+        Ok((resp.bytes().await?.to_vec(), content_type))
+    }
 }
@@ -1,15 +1,25 @@
 #![allow(dead_code)]
 
-use crate::gql::Review;
-use crate::settings::Settings;
+use crate::audit::AuditLog;
+use crate::events::EventBus;
+use crate::gql::client::MensattGqlClient;
+use crate::settings::{Settings, SinkKind};
+use crate::sinks::moderation::Moderator;
+use crate::sinks::NotificationSink;
+use crate::store::ReviewStore;
 use config::Config;
 use log::{debug, info};
 use rustls::crypto::CryptoProvider;
+use std::sync::Arc;
 
+mod audit;
 mod discord;
+mod events;
 mod gql;
 mod image;
 mod settings;
+mod sinks;
+mod store;
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
@@ -36,32 +46,90 @@ async fn main() -> anyhow::Result<()> {
 
     info!("Starting up notifier service...");
 
-    // Buffer size shouldn't really matter here, as I don't expect the receiver to take that long
-    let (tx, rx) = tokio::sync::mpsc::channel::<Review>(8);
+    // Buffer size shouldn't really matter here, as I don't expect the receivers to take that long
+    let events = EventBus::new(64);
+    // Shared, so that other sinks can link to the discord messages
+    let store = Arc::new(ReviewStore::open(&settings).expect("Could not open review store"));
+    // Shared, so that decisions made in any sink end up in the same log
+    let audit_log = Arc::new(AuditLog::open(&settings));
+    let moderator = Arc::new(Moderator::new(
+        settings.clone(),
+        events.clone(),
+        audit_log.clone(),
+        store.clone(),
+    ));
 
-    // Required as both futures use async move, thus they "invalidate" settings
-    // Consequently, we give one future one clone and the other one the original
-    // I think it would also be fine to just pass references, but that could involve lifetimes
-    let settings_dup = settings.clone();
+    let mut sinks: Vec<Arc<dyn NotificationSink>> = vec![];
+    for kind in &settings.sinks {
+        info!("Creating {:?} sink", kind);
+        let sink: Arc<dyn NotificationSink> = match kind {
+            SinkKind::Discord => Arc::new(
+                discord::bot::Bot::start(
+                    events.clone(),
+                    settings.clone(),
+                    store.clone(),
+                    audit_log.clone(),
+                )
+                .await
+                .expect("Failed to start bot"),
+            ),
+            SinkKind::Webhook => Arc::new(
+                sinks::webhook::WebhookSink::new(settings.clone())
+                    .expect("Failed to create webhook sink"),
+            ),
+            SinkKind::Matrix => {
+                sinks::matrix::MatrixSink::start(settings.clone(), moderator.clone())
+                    .await
+                    .expect("Failed to start matrix sink")
+            }
+            SinkKind::Email => sinks::email::EmailSink::start(settings.clone())
+                .expect("Failed to start email sink"),
+            SinkKind::Telegram => {
+                sinks::telegram::TelegramSink::start(settings.clone(), moderator.clone())
+                    .await
+                    .expect("Failed to start telegram sink")
+            }
+            SinkKind::Slack => sinks::slack::SlackSink::start(settings.clone(), moderator.clone())
+                .await
+                .expect("Failed to start slack sink"),
+            SinkKind::Push => Arc::new(
+                sinks::push::PushSink::new(settings.clone()).expect("Failed to create push sink"),
+            ),
+            SinkKind::Mqtt => Arc::new(
+                sinks::mqtt::MqttSink::start(settings.clone()).expect("Failed to start mqtt sink"),
+            ),
+            SinkKind::Stream => sinks::stream::StreamSink::start(settings.clone())
+                .await
+                .expect("Failed to start stream sink"),
+        };
+        sinks.push(sink);
+    }
+
+    // Sinks subscribe before the listener is started, so that they don't miss any events
+    let gql_client = Arc::new(MensattGqlClient::new(settings.clone()));
+    let sink_tasks = sinks
+        .into_iter()
+        .map(|sink| {
+            tokio::spawn(sinks::forward_events(
+                sink,
+                events.subscribe(),
+                gql_client.clone(),
+            ))
+        })
+        .collect::<Vec<_>>();
 
     // Create GQL listener
+    let mut listener = gql::listener::ReviewListener::new(settings, events);
     let gql_task = tokio::spawn(async move {
-        let listener = gql::listener::ReviewListener::new(settings_dup, tx);
         listener.continuous_listen().await;
     });
 
-    // Create discord bot
-    let discord_handle = tokio::spawn(async move {
-        discord::bot::Bot::new(rx, settings.clone())
-            .start()
-            .await
-            .expect("Failed to start bot");
-    });
-
     info!("Notifier service started!");
 
     // Tasks finishing is equivalent to them failing, as they should run forever
-    discord_handle.await.expect("Discord bot failed");
+    for task in sink_tasks {
+        task.await.expect("Sink failed");
+    }
     gql_task.await.expect("GQL listener failed");
 
     Ok(())
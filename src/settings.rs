@@ -1,4 +1,10 @@
-use serde::Deserialize;
+use chrono::Weekday;
+use serde::{Deserialize, Deserializer};
+
+// Value of the menu option for entering a reason by hand
+pub const OTHER_REASON: &str = "Other";
+// Discord refuses menus with longer option values
+const MAX_REJECT_REASON_LEN: usize = 100;
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct Settings {
@@ -6,6 +12,39 @@ pub struct Settings {
     pub graphql: GraphQl,
     pub mensatt: Mensatt,
     pub image: Image,
+    #[serde(default)]
+    pub store: Store,
+    #[serde(default)]
+    pub audit: Audit,
+    // Where new reviews are sent to, each sink is configured in its own section
+    #[serde(default = "default_sinks")]
+    pub sinks: Vec<SinkKind>,
+    pub webhook: Option<Webhook>,
+    pub matrix: Option<Matrix>,
+    pub email: Option<Email>,
+    pub telegram: Option<Telegram>,
+    pub slack: Option<Slack>,
+    pub push: Option<Push>,
+    pub mqtt: Option<Mqtt>,
+    pub stream: Option<Stream>,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum SinkKind {
+    Discord,
+    Webhook,
+    Matrix,
+    Email,
+    Telegram,
+    Slack,
+    Push,
+    Mqtt,
+    Stream,
+}
+
+fn default_sinks() -> Vec<SinkKind> {
+    vec![SinkKind::Discord]
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -13,12 +52,178 @@ pub struct Discord {
     pub token: String,
     pub comm_channel: u64,
     pub guilds: Vec<u64>,
+    #[serde(default)]
+    pub roles: Roles,
+    // Offered when rejecting a review, in addition to entering a reason by hand
+    #[serde(
+        default = "default_reject_reasons",
+        deserialize_with = "deserialize_reject_reasons"
+    )]
+    pub reject_reasons: Vec<String>,
+    // Reviews matching a rule need that many approvals, the highest of all matching rules counts
+    #[serde(default)]
+    pub quorum: Vec<QuorumRule>,
+    // How long a moderator can claim a review for, before others can moderate it again
+    #[serde(default = "default_claim_timeout_secs")]
+    pub claim_timeout_secs: u64,
+}
+
+fn default_claim_timeout_secs() -> u64 {
+    600
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct QuorumRule {
+    // Conditions left out match every review, conditions given must all match
+    pub max_stars: Option<i32>,
+    pub has_images: Option<bool>,
+    pub approvals: usize,
+}
+
+impl Discord {
+    /// Whether some reviews need more than one approval, which only the discord bot can collect
+    pub fn has_quorum(&self) -> bool {
+        self.quorum.iter().any(|rule| rule.approvals > 1)
+    }
+
+    /// Approvals needed by a review, the strictest of all matching rules applies
+    pub fn get_required_approvals(&self, stars: i32, has_images: bool) -> usize {
+        self.quorum
+            .iter()
+            .filter(|rule| rule.matches(stars, has_images))
+            .map(|rule| rule.approvals)
+            .max()
+            .unwrap_or(1)
+            .max(1)
+    }
+}
+
+impl QuorumRule {
+    pub fn matches(&self, stars: i32, has_images: bool) -> bool {
+        self.max_stars.is_none_or(|max_stars| stars <= max_stars)
+            && self.has_images.is_none_or(|images| images == has_images)
+    }
+}
+
+fn default_reject_reasons() -> Vec<String> {
+    [
+        "Spam",
+        "Offensive",
+        "Off-topic",
+        "Personal data",
+        "Wrong dish",
+    ]
+    .iter()
+    .map(|reason| reason.to_string())
+    .collect()
+}
+
+/// Refuses reasons discord wouldn't accept in a menu, which would break rejecting altogether
+fn deserialize_reject_reasons<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<String>, D::Error> {
+    let reasons = Vec::<String>::deserialize(deserializer)?;
+    validate_reject_reasons(&reasons).map_err(serde::de::Error::custom)?;
+    Ok(reasons)
+}
+
+fn validate_reject_reasons(reasons: &[String]) -> Result<(), String> {
+    for (i, reason) in reasons.iter().enumerate() {
+        if reason.is_empty() {
+            return Err("Reject reasons must not be empty".to_string());
+        }
+        if reason.chars().count() > MAX_REJECT_REASON_LEN {
+            return Err(format!(
+                "Reject reason {} is longer than {} characters",
+                reason, MAX_REJECT_REASON_LEN
+            ));
+        }
+        if reason == OTHER_REASON {
+            return Err(format!(
+                "Reject reason {} is always offered and must not be configured",
+                OTHER_REASON
+            ));
+        }
+        if reasons[..i].contains(reason) {
+            return Err(format!("Reject reason {} is configured twice", reason));
+        }
+    }
+    Ok(())
+}
+
+// Role ids allowed to perform each action, everyone may perform an action without any roles
+#[derive(Debug, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct Roles {
+    // Also covers claiming a review
+    pub approve: Vec<u64>,
+    // Also covers unapproving an approved review
+    pub reject: Vec<u64>,
+    // Also covers removing single images
+    pub delete: Vec<u64>,
+    pub rotate: Vec<u64>,
+    pub edit: Vec<u64>,
+    pub recover: Vec<u64>,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Action {
+    Approve,
+    Reject,
+    Delete,
+    Rotate,
+    Edit,
+    Recover,
+}
+
+impl Roles {
+    pub fn get(&self, action: Action) -> &[u64] {
+        match action {
+            Action::Approve => &self.approve,
+            Action::Reject => &self.reject,
+            Action::Delete => &self.delete,
+            Action::Rotate => &self.rotate,
+            Action::Edit => &self.edit,
+            Action::Recover => &self.recover,
+        }
+    }
 }
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct GraphQl {
     pub ws_url: String,
     pub https_url: String,
+    #[serde(default)]
+    pub reconnect: Reconnect,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct Reconnect {
+    // Delay before the first reconnect attempt, doubled with every failed attempt
+    pub initial_backoff_secs: u64,
+    pub max_backoff_secs: u64,
+    // Fraction by which the delay is randomly varied, e.g. 0.2 means +-20%
+    pub jitter: f64,
+    // How long a connection must have lasted for the backoff to be reset
+    pub stable_after_secs: u64,
+    pub keep_alive_interval_secs: u64,
+    pub keep_alive_retries: usize,
+    pub subscription_buffer_size: usize,
+}
+
+impl Default for Reconnect {
+    fn default() -> Self {
+        Self {
+            initial_backoff_secs: 5,
+            max_backoff_secs: 300,
+            jitter: 0.2,
+            stable_after_secs: 300,
+            keep_alive_interval_secs: 30,
+            keep_alive_retries: 10,
+            subscription_buffer_size: 32,
+        }
+    }
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -37,3 +242,226 @@ pub struct Image {
     pub rotate_url: String,
     pub key: String,
 }
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct Store {
+    // JSON file in which the review to discord message mapping is persisted
+    pub path: String,
+}
+
+impl Default for Store {
+    fn default() -> Self {
+        Self {
+            path: "reviews.json".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct Audit {
+    // JSON lines file every moderation decision is appended to
+    pub path: String,
+    // Discord channel every moderation decision is additionally posted to
+    pub channel: Option<u64>,
+}
+
+impl Default for Audit {
+    fn default() -> Self {
+        Self {
+            path: "audit.jsonl".to_string(),
+            channel: None,
+        }
+    }
+}
+
+impl Audit {
+    /// The audit channel, 0 is treated as unset as no channel can have that id
+    pub fn get_channel(&self) -> Option<u64> {
+        self.channel.filter(|channel| *channel != 0)
+    }
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct Webhook {
+    // How often a failed delivery is retried, with the delay doubling each time up to 5 minutes
+    pub max_retries: u32,
+    pub initial_backoff_secs: u64,
+    pub endpoints: Vec<WebhookEndpoint>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct WebhookEndpoint {
+    pub url: String,
+    // Used to sign the payload, so the receiver can verify it came from us
+    pub secret: String,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct Matrix {
+    pub homeserver_url: String,
+    pub access_token: String,
+    // Internal room id (e.g. !abc:example.org), not an alias
+    pub room_id: String,
+    // User ids (e.g. @alice:example.org) whose reactions moderate reviews, nobody's without any
+    #[serde(default)]
+    pub moderators: Vec<String>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct Email {
+    pub smtp_host: String,
+    pub smtp_port: u16,
+    pub tls: EmailTls,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    // Sender and recipient addresses, e.g. "Mensatt <noreply@example.org>"
+    pub from: String,
+    pub recipients: Vec<String>,
+    pub schedule: EmailSchedule,
+    // Hour of the day (UTC) at which the digest is sent
+    pub hour: u32,
+    // Only used for the weekly schedule, e.g. "Mon"
+    pub weekday: Option<Weekday>,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum EmailTls {
+    // Plain text, only meant for local testing
+    None,
+    Starttls,
+    Tls,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum EmailSchedule {
+    Daily,
+    Weekly,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct Telegram {
+    // Can be pointed at a local mock of the bot api
+    #[serde(default = "default_telegram_api_url")]
+    pub api_url: String,
+    pub bot_token: String,
+    pub chat_id: i64,
+    // Numeric user ids whose buttons moderate reviews, nobody's without any
+    #[serde(default)]
+    pub moderators: Vec<i64>,
+}
+
+fn default_telegram_api_url() -> String {
+    "https://api.telegram.org".to_string()
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct Slack {
+    #[serde(default = "default_slack_api_url")]
+    pub api_url: String,
+    // Bot user OAuth token (xoxb-...), needs the chat:write and files:write scopes
+    pub bot_token: String,
+    // Used to verify that interactivity requests actually come from slack
+    pub signing_secret: String,
+    // Channel id (e.g. C0123456789), not the name
+    pub channel: String,
+    // Address of the interactivity endpoint, slack must be able to reach it at /slack/actions
+    pub listen_addr: String,
+    // Member ids (e.g. U0123456789) whose buttons moderate reviews, nobody's without any
+    #[serde(default)]
+    pub moderators: Vec<String>,
+}
+
+fn default_slack_api_url() -> String {
+    "https://slack.com/api".to_string()
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct Push {
+    pub service: PushService,
+    // e.g. https://ntfy.sh or https://gotify.example.org
+    pub server_url: String,
+    // Only used by ntfy, gotify decides by the token which application the message belongs to
+    pub topic: Option<String>,
+    // Access token for ntfy or application token for gotify
+    pub token: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum PushService {
+    Ntfy,
+    Gotify,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct Mqtt {
+    pub host: String,
+    pub port: u16,
+    #[serde(default)]
+    pub tls: bool,
+    #[serde(default = "default_mqtt_client_id")]
+    pub client_id: String,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    // Events are published below it, e.g. mensatt/reviews/created
+    #[serde(default = "default_mqtt_topic_prefix")]
+    pub topic_prefix: String,
+    // 0 (at most once), 1 (at least once) or 2 (exactly once)
+    #[serde(default = "default_mqtt_qos")]
+    pub qos: u8,
+    // Lets new subscribers see the last event of every topic right away
+    #[serde(default)]
+    pub retain: bool,
+}
+
+fn default_mqtt_client_id() -> String {
+    "notifier-rs".to_string()
+}
+
+fn default_mqtt_topic_prefix() -> String {
+    "mensatt/reviews".to_string()
+}
+
+fn default_mqtt_qos() -> u8 {
+    1
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct Stream {
+    pub listen_addr: String,
+    // Clients authenticate with it as a bearer token, or the token query parameter for browsers
+    pub token: String,
+    // How many of the latest events are sent to a client right after it connects
+    #[serde(default = "default_stream_replay")]
+    pub replay: usize,
+}
+
+fn default_stream_replay() -> usize {
+    50
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reasons(reasons: &[&str]) -> Vec<String> {
+        reasons.iter().map(|reason| reason.to_string()).collect()
+    }
+
+    #[test]
+    fn validate_default_reject_reasons() {
+        assert!(validate_reject_reasons(&default_reject_reasons()).is_ok());
+    }
+
+    #[test]
+    fn validate_invalid_reject_reasons() {
+        assert!(validate_reject_reasons(&reasons(&["Spam", "Spam"])).is_err());
+        assert!(validate_reject_reasons(&reasons(&["Spam", OTHER_REASON])).is_err());
+        assert!(validate_reject_reasons(&reasons(&[""])).is_err());
+        assert!(validate_reject_reasons(&["x".repeat(MAX_REJECT_REASON_LEN + 1)]).is_err());
+        assert!(validate_reject_reasons(&["x".repeat(MAX_REJECT_REASON_LEN)]).is_ok());
+    }
+}
@@ -0,0 +1,368 @@
+use crate::gql::Review;
+use crate::settings::{Email, EmailSchedule, EmailTls, Settings};
+use crate::sinks::payload::ReviewPayload;
+use crate::sinks::render::{escape_html, get_stars};
+use crate::sinks::{NotificationSink, ReviewUpdate};
+use async_trait::async_trait;
+use chrono::{DateTime, Datelike, Duration, NaiveTime, Utc, Weekday};
+use lettre::message::{Mailbox, MultiPart};
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use log::{info, warn};
+use std::sync::{Arc, Mutex};
+
+#[derive(Default)]
+struct Digest {
+    created: Vec<ReviewPayload>,
+    approved: Vec<ReviewPayload>,
+}
+
+impl Digest {
+    fn is_empty(&self) -> bool {
+        self.created.is_empty() && self.approved.is_empty()
+    }
+}
+
+/// Collects new and approved reviews and mails them out as a digest on a fixed schedule.
+pub struct EmailSink {
+    settings: Settings,
+    email: Email,
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+    recipients: Vec<Mailbox>,
+    // NOTE: This is only kept in memory, reviews collected before a restart are not sent
+    digest: Mutex<Digest>,
+}
+
+impl EmailSink {
+    pub fn start(settings: Settings) -> anyhow::Result<Arc<Self>> {
+        let email = settings.email.clone().ok_or_else(|| {
+            anyhow::anyhow!("Email sink is enabled, but there is no [email] section")
+        })?;
+        if email.hour > 23 {
+            anyhow::bail!("Email digest hour must be between 0 and 23");
+        }
+
+        let builder = match email.tls {
+            EmailTls::None => {
+                AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&email.smtp_host)
+            }
+            EmailTls::Starttls => {
+                AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&email.smtp_host)?
+            }
+            EmailTls::Tls => AsyncSmtpTransport::<Tokio1Executor>::relay(&email.smtp_host)?,
+        };
+        let mut builder = builder.port(email.smtp_port);
+        if let Some(username) = &email.username {
+            builder = builder.credentials(Credentials::new(
+                username.clone(),
+                email.password.clone().unwrap_or_default(),
+            ));
+        }
+
+        let sink = Arc::new(Self {
+            transport: builder.build(),
+            from: email.from.parse()?,
+            recipients: email
+                .recipients
+                .iter()
+                .map(|recipient| recipient.parse())
+                .collect::<Result<_, _>>()?,
+            settings,
+            email,
+            digest: Mutex::new(Digest::default()),
+        });
+
+        tokio::spawn(sink.clone().send_on_schedule());
+
+        Ok(sink)
+    }
+
+    async fn send_on_schedule(self: Arc<Self>) {
+        loop {
+            let next = get_next_send_time(&self.email, Utc::now());
+            info!("Next review digest will be sent at {}", next);
+            // Can only fail for a negative duration, in which case we are late anyway
+            tokio::time::sleep((next - Utc::now()).to_std().unwrap_or_default()).await;
+
+            let digest = std::mem::take(&mut *self.digest.lock().unwrap());
+            if digest.is_empty() {
+                info!("No reviews since the last digest, not sending one");
+                continue;
+            }
+
+            match self.send_digest(&digest).await {
+                Ok(_) => info!(
+                    "Sent review digest with {} new and {} approved reviews",
+                    digest.created.len(),
+                    digest.approved.len()
+                ),
+                Err(err) => {
+                    warn!(
+                        "Could not send review digest, trying again next time: {}",
+                        err
+                    );
+                    // Put the reviews back in front of whatever arrived in the meantime
+                    let mut current = self.digest.lock().unwrap();
+                    let mut created = digest.created;
+                    created.append(&mut current.created);
+                    current.created = created;
+                    let mut approved = digest.approved;
+                    approved.append(&mut current.approved);
+                    current.approved = approved;
+                }
+            }
+        }
+    }
+
+    async fn send_digest(&self, digest: &Digest) -> anyhow::Result<()> {
+        let subject = format!(
+            "Mensatt reviews: {} new, {} approved",
+            digest.created.len(),
+            digest.approved.len()
+        );
+
+        let mut builder = Message::builder().from(self.from.clone()).subject(subject);
+        for recipient in &self.recipients {
+            builder = builder.to(recipient.clone());
+        }
+        let message = builder.multipart(MultiPart::alternative_plain_html(
+            render_plain(digest),
+            render_html(digest),
+        ))?;
+
+        self.transport.send(message).await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl NotificationSink for EmailSink {
+    fn name(&self) -> &str {
+        "email"
+    }
+
+    async fn deliver_review(&self, review: &Review) -> anyhow::Result<()> {
+        let mut digest = self.digest.lock().unwrap();
+        if !digest.created.iter().any(|r| r.id == review.id.0) {
+            digest
+                .created
+                .push(ReviewPayload::new(&self.settings, review));
+        }
+        Ok(())
+    }
+
+    async fn update_review_state(&self, update: &ReviewUpdate) -> anyhow::Result<()> {
+        let mut digest = self.digest.lock().unwrap();
+        match update {
+            ReviewUpdate::Accepted(review) => {
+                if !digest.approved.iter().any(|r| r.id == review.id.0) {
+                    digest
+                        .approved
+                        .push(ReviewPayload::new(&self.settings, review));
+                }
+            }
+            ReviewUpdate::Edited(review) => {
+                let payload = ReviewPayload::new(&self.settings, review);
+                let digest = &mut *digest;
+                for r in digest
+                    .created
+                    .iter_mut()
+                    .chain(digest.approved.iter_mut())
+                    .filter(|r| r.id == review.id.0)
+                {
+                    *r = payload.clone();
+                }
+            }
+            ReviewUpdate::Unapproved(review_id) | ReviewUpdate::Rejected(review_id) => {
+                digest.approved.retain(|r| r.id != review_id.0);
+            }
+            ReviewUpdate::Deleted(review_id) => {
+                digest.created.retain(|r| r.id != review_id.0);
+                digest.approved.retain(|r| r.id != review_id.0);
+            }
+        }
+        Ok(())
+    }
+}
+
+fn get_next_send_time(email: &Email, now: DateTime<Utc>) -> DateTime<Utc> {
+    // Checked when starting the sink
+    let time = NaiveTime::from_hms_opt(email.hour, 0, 0).unwrap_or_default();
+    let mut next = now.date_naive().and_time(time).and_utc();
+    if next <= now {
+        next += Duration::days(1);
+    }
+
+    if let EmailSchedule::Weekly = email.schedule {
+        let weekday = email.weekday.unwrap_or(Weekday::Mon);
+        while next.weekday() != weekday {
+            next += Duration::days(1);
+        }
+    }
+
+    next
+}
+
+fn render_plain(digest: &Digest) -> String {
+    let mut plain = String::new();
+    for (heading, reviews) in [
+        ("New reviews", &digest.created),
+        ("Approved reviews", &digest.approved),
+    ] {
+        if reviews.is_empty() {
+            continue;
+        }
+        plain.push_str(&format!("{}\n{}\n\n", heading, "=".repeat(heading.len())));
+        for review in reviews {
+            plain.push_str(&format!(
+                "{} | {}\n{}\n",
+                review.dish,
+                get_stars(review.stars),
+                review.occurrence_url
+            ));
+            if let Some(text) = review.text.as_ref().filter(|text| !text.is_empty()) {
+                plain.push_str(&format!("{}\n", text));
+            }
+            // Image urls need the key of the image api, mensatt shows them to everyone
+            if !review.image_urls.is_empty() {
+                plain.push_str(&format!(
+                    "{} on mensatt\n",
+                    get_image_count(review.image_urls.len())
+                ));
+            }
+            plain.push('\n');
+        }
+    }
+    plain
+}
+
+fn render_html(digest: &Digest) -> String {
+    let mut html = String::from("<html><body>");
+    for (heading, reviews) in [
+        ("New reviews", &digest.created),
+        ("Approved reviews", &digest.approved),
+    ] {
+        if reviews.is_empty() {
+            continue;
+        }
+        html.push_str(&format!("<h2>{}</h2>", heading));
+        for review in reviews {
+            html.push_str(&format!(
+                "<h3><a href=\"{}\">{}</a> | {}</h3>",
+                escape_html(&review.occurrence_url),
+                escape_html(&review.dish),
+                get_stars(review.stars)
+            ));
+            if let Some(text) = review.text.as_ref().filter(|text| !text.is_empty()) {
+                html.push_str(&format!(
+                    "<p>{}</p>",
+                    escape_html(text).replace('\n', "<br>")
+                ));
+            }
+            if !review.image_urls.is_empty() {
+                html.push_str(&format!(
+                    "<p><a href=\"{}\">{} on mensatt</a></p>",
+                    escape_html(&review.occurrence_url),
+                    get_image_count(review.image_urls.len())
+                ));
+            }
+        }
+    }
+    html.push_str("</body></html>");
+    html
+}
+
+fn get_image_count(count: usize) -> String {
+    match count {
+        1 => "1 image".to_string(),
+        _ => format!("{} images", count),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn get_email(schedule: EmailSchedule, hour: u32, weekday: Option<Weekday>) -> Email {
+        Email {
+            smtp_host: "smtp.example.org".to_string(),
+            smtp_port: 587,
+            tls: EmailTls::Starttls,
+            username: None,
+            password: None,
+            from: "noreply@example.org".to_string(),
+            recipients: vec![],
+            schedule,
+            hour,
+            weekday,
+        }
+    }
+
+    fn at(year: i32, month: u32, day: u32, hour: u32, minute: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(year, month, day, hour, minute, 0)
+            .unwrap()
+    }
+
+    #[test]
+    fn daily_later_today() {
+        let email = get_email(EmailSchedule::Daily, 18, None);
+        assert_eq!(
+            get_next_send_time(&email, at(2026, 10, 14, 9, 30)),
+            at(2026, 10, 14, 18, 0)
+        );
+    }
+
+    #[test]
+    fn daily_wraps_to_tomorrow() {
+        let email = get_email(EmailSchedule::Daily, 8, None);
+        assert_eq!(
+            get_next_send_time(&email, at(2026, 10, 14, 9, 30)),
+            at(2026, 10, 15, 8, 0)
+        );
+        // Exactly at the time the digest was just sent
+        assert_eq!(
+            get_next_send_time(&email, at(2026, 10, 14, 8, 0)),
+            at(2026, 10, 15, 8, 0)
+        );
+        // Across the end of the year
+        assert_eq!(
+            get_next_send_time(&email, at(2026, 12, 31, 23, 0)),
+            at(2027, 1, 1, 8, 0)
+        );
+    }
+
+    #[test]
+    fn weekly_wraps_to_next_week() {
+        // 2026-10-14 is a Wednesday
+        let email = get_email(EmailSchedule::Weekly, 8, Some(Weekday::Mon));
+        assert_eq!(
+            get_next_send_time(&email, at(2026, 10, 14, 9, 30)),
+            at(2026, 10, 19, 8, 0)
+        );
+
+        // Same weekday, but the hour has passed
+        let email = get_email(EmailSchedule::Weekly, 8, Some(Weekday::Wed));
+        assert_eq!(
+            get_next_send_time(&email, at(2026, 10, 14, 9, 30)),
+            at(2026, 10, 21, 8, 0)
+        );
+
+        // Same weekday before the hour
+        let email = get_email(EmailSchedule::Weekly, 18, Some(Weekday::Wed));
+        assert_eq!(
+            get_next_send_time(&email, at(2026, 10, 14, 9, 30)),
+            at(2026, 10, 14, 18, 0)
+        );
+    }
+
+    #[test]
+    fn weekly_defaults_to_monday() {
+        let email = get_email(EmailSchedule::Weekly, 8, None);
+        assert_eq!(
+            get_next_send_time(&email, at(2026, 10, 18, 9, 30)),
+            at(2026, 10, 19, 8, 0)
+        );
+    }
+}
@@ -0,0 +1,568 @@
+use crate::gql::{Image, Review};
+use crate::image::ImageClient;
+use crate::settings::{Matrix, Settings};
+use crate::sinks::moderation::{Messages, Moderator, ReviewInfo, ReviewMessage};
+use crate::sinks::render::{self, escape_html, get_author, get_title, get_url};
+use crate::sinks::{NotificationSink, ReviewUpdate};
+use crate::store::ModerationState;
+use async_trait::async_trait;
+use log::{debug, info, warn};
+use reqwest::Url;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+const APPROVE_KEY: &str = "✅";
+const DELETE_KEY: &str = "🗑";
+
+// Custom content field of our events, so that reactions can be traced back to the review even
+// after a restart
+const REVIEW_FIELD: &str = "de.mensatt.review";
+
+#[derive(Clone)]
+struct MatrixMessage {
+    event_id: String,
+    image_event_ids: Vec<String>,
+    // The rendered review, without the status line
+    body: String,
+    formatted_body: String,
+}
+
+#[derive(Deserialize)]
+struct SyncResponse {
+    next_batch: String,
+    #[serde(default)]
+    rooms: SyncRooms,
+}
+
+#[derive(Deserialize, Default)]
+struct SyncRooms {
+    #[serde(default)]
+    join: HashMap<String, JoinedRoom>,
+}
+
+#[derive(Deserialize)]
+struct JoinedRoom {
+    #[serde(default)]
+    timeline: Timeline,
+}
+
+#[derive(Deserialize, Default)]
+struct Timeline {
+    #[serde(default)]
+    events: Vec<RoomEvent>,
+}
+
+#[derive(Deserialize)]
+struct RoomEvent {
+    #[serde(rename = "type")]
+    kind: String,
+    sender: String,
+    #[serde(default)]
+    content: Value,
+}
+
+/// Posts new reviews into a Matrix room through the client-server API.
+///
+/// Configured moderators approve (✅) or delete (🗑) a review by reacting to its message, which is
+/// then edited to show who did it. Approving is left to discord if reviews may need several approvals.
+pub struct MatrixSink {
+    settings: Settings,
+    matrix: Matrix,
+    client: reqwest::Client,
+    moderator: Arc<Moderator>,
+    image_client: ImageClient,
+    user_id: String,
+    messages: Messages<MatrixMessage>,
+}
+
+impl MatrixSink {
+    pub async fn start(settings: Settings, moderator: Arc<Moderator>) -> anyhow::Result<Arc<Self>> {
+        let matrix = settings.matrix.clone().ok_or_else(|| {
+            anyhow::anyhow!("Matrix sink is enabled, but there is no [matrix] section")
+        })?;
+
+        let mut default_headers = reqwest::header::HeaderMap::new();
+        default_headers.insert(
+            "Authorization",
+            format!("Bearer {}", matrix.access_token).parse()?,
+        );
+        let client = reqwest::Client::builder()
+            .default_headers(default_headers)
+            .build()?;
+
+        let whoami: Value = client
+            .get(endpoint(
+                &matrix.homeserver_url,
+                &["_matrix", "client", "v3", "account", "whoami"],
+            )?)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        let user_id = whoami["user_id"]
+            .as_str()
+            .ok_or_else(|| anyhow::anyhow!("Homeserver did not tell us who we are"))?
+            .to_string();
+        info!("Logged in to matrix as {}", user_id);
+        if matrix.moderators.is_empty() {
+            warn!("No matrix moderators are configured, reactions to reviews are ignored");
+        }
+
+        let sink = Arc::new(Self {
+            moderator,
+            image_client: ImageClient::new(settings.clone()),
+            settings,
+            matrix,
+            client,
+            user_id,
+            messages: Messages::default(),
+        });
+
+        tokio::spawn(sink.clone().listen_for_reactions());
+
+        Ok(sink)
+    }
+
+    fn room_endpoint(&self, segments: &[&str]) -> anyhow::Result<Url> {
+        let mut all = vec!["_matrix", "client", "v3", "rooms", &self.matrix.room_id];
+        all.extend_from_slice(segments);
+        endpoint(&self.matrix.homeserver_url, &all)
+    }
+
+    async fn send_event(&self, event_type: &str, content: &Value) -> anyhow::Result<String> {
+        // Transaction ids only have to be unique for our access token
+        let txn_id = format!("notifier-{}", rand::random::<u64>());
+
+        let resp: Value = self
+            .client
+            .put(self.room_endpoint(&["send", event_type, &txn_id])?)
+            .json(content)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        resp["event_id"]
+            .as_str()
+            .map(|event_id| event_id.to_string())
+            .ok_or_else(|| anyhow::anyhow!("Homeserver did not return an event id"))
+    }
+
+    async fn send_image(&self, review: &Review, image: &Image) -> anyhow::Result<String> {
+        let (data, content_type) = self.image_client.download_image(&image.id.0).await?;
+        let size = data.len();
+
+        let mut upload_url = endpoint(
+            &self.matrix.homeserver_url,
+            &["_matrix", "media", "v3", "upload"],
+        )?;
+        upload_url
+            .query_pairs_mut()
+            .append_pair("filename", &image.id.0);
+        let resp: Value = self
+            .client
+            .post(upload_url)
+            .header("Content-Type", &content_type)
+            .body(data)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        let content_uri = resp["content_uri"]
+            .as_str()
+            .ok_or_else(|| anyhow::anyhow!("Homeserver did not return a content uri"))?;
+
+        self.send_event(
+            "m.room.message",
+            &json!({
+                "msgtype": "m.image",
+                "body": image.id.0,
+                "url": content_uri,
+                "info": { "mimetype": content_type, "size": size },
+                REVIEW_FIELD: { "id": review.id.0 },
+            }),
+        )
+        .await
+    }
+
+    /// Replaces the content of the review's message according to its current state
+    async fn edit_message(&self, message: &ReviewMessage<MatrixMessage>) -> anyhow::Result<()> {
+        let status = get_status(
+            &message.state,
+            message.who.as_deref(),
+            self.moderator.can_approve(&message.info),
+        );
+        let new_content = json!({
+            "msgtype": "m.text",
+            "body": format!("{}\n\n{}", message.content.body, status),
+            "format": "org.matrix.custom.html",
+            "formatted_body": format!("{}<p><em>{}</em></p>", message.content.formatted_body, escape_html(&status)),
+        });
+
+        self.send_event(
+            "m.room.message",
+            &json!({
+                "msgtype": "m.text",
+                "body": format!("* {}", new_content["body"].as_str().unwrap_or_default()),
+                "m.new_content": new_content,
+                "m.relates_to": { "rel_type": "m.replace", "event_id": message.content.event_id },
+            }),
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    async fn listen_for_reactions(self: Arc<Self>) {
+        let mut since: Option<String> = None;
+        loop {
+            let sync = match self.sync(since.as_deref()).await {
+                Ok(sync) => sync,
+                Err(err) => {
+                    warn!("Matrix sync failed, trying again in 10 seconds: {}", err);
+                    tokio::time::sleep(Duration::from_secs(10)).await;
+                    continue;
+                }
+            };
+
+            // The first sync only tells us where we are, reactions from before we started have
+            // either been handled already or are too old to act upon
+            if since.is_some() {
+                let events = sync
+                    .rooms
+                    .join
+                    .get(&self.matrix.room_id)
+                    .map(|room| room.timeline.events.as_slice())
+                    .unwrap_or_default();
+                for event in events {
+                    if let Err(err) = self.handle_reaction(event).await {
+                        warn!("Could not handle matrix reaction: {}", err);
+                    }
+                }
+            }
+
+            since = Some(sync.next_batch);
+        }
+    }
+
+    async fn sync(&self, since: Option<&str>) -> anyhow::Result<SyncResponse> {
+        let filter = json!({
+            "presence": { "types": [] },
+            "account_data": { "types": [] },
+            "room": {
+                "rooms": [self.matrix.room_id],
+                "state": { "types": [] },
+                "ephemeral": { "types": [] },
+                "account_data": { "types": [] },
+                "timeline": { "types": ["m.reaction"] },
+            },
+        });
+
+        let mut url = endpoint(
+            &self.matrix.homeserver_url,
+            &["_matrix", "client", "v3", "sync"],
+        )?;
+        {
+            let mut query = url.query_pairs_mut();
+            query.append_pair("filter", &filter.to_string());
+            match since {
+                Some(since) => query
+                    .append_pair("since", since)
+                    .append_pair("timeout", "30000"),
+                None => query.append_pair("timeout", "0"),
+            };
+        }
+
+        Ok(self
+            .client
+            .get(url)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?)
+    }
+
+    async fn handle_reaction(&self, event: &RoomEvent) -> anyhow::Result<()> {
+        if event.kind != "m.reaction" || event.sender == self.user_id {
+            return Ok(());
+        }
+
+        let relation = &event.content["m.relates_to"];
+        let (target, key) = match (relation["event_id"].as_str(), relation["key"].as_str()) {
+            // Clients may or may not send the emoji variation selector
+            (Some(target), Some(key)) => (target, key.trim_end_matches('\u{fe0f}')),
+            _ => return Ok(()),
+        };
+        if key != APPROVE_KEY && key != DELETE_KEY {
+            return Ok(());
+        }
+        if !self.matrix.moderators.contains(&event.sender) {
+            debug!("Ignoring reaction of {}, who is no moderator", event.sender);
+            return Ok(());
+        }
+
+        let review_id = match self.find_review(target).await? {
+            Some(review_id) => review_id,
+            None => {
+                debug!("Reaction to {} is not about a review", target);
+                return Ok(());
+            }
+        };
+
+        let info = self
+            .messages
+            .get(&review_id)
+            .map(|message| message.info)
+            .unwrap_or_default();
+        if key == APPROVE_KEY && !self.moderator.can_approve(&info) {
+            debug!(
+                "Ignoring approval, as review {} may need several approvals",
+                review_id
+            );
+            return Ok(());
+        }
+
+        let state = if key == APPROVE_KEY {
+            ModerationState::Approved
+        } else {
+            ModerationState::Deleted
+        };
+        info!(
+            "{} reacted with {} to review {}",
+            event.sender, key, review_id
+        );
+
+        let current = self.messages.get_state(&review_id);
+        if current == Some(state) || current == Some(ModerationState::Deleted) {
+            debug!("Review {} is already {:?}", review_id, current);
+            return Ok(());
+        }
+
+        self.moderator
+            .moderate(
+                self.name(),
+                &self.messages,
+                &review_id,
+                state,
+                &event.sender,
+            )
+            .await?;
+
+        match self.messages.get(&review_id) {
+            Some(message) => self.edit_message(&message).await,
+            None => {
+                debug!(
+                    "Review {} has no matrix message, nothing to update",
+                    review_id
+                );
+                Ok(())
+            }
+        }
+    }
+
+    /// Finds the review an event belongs to, looking at the event itself if we don't know it
+    async fn find_review(&self, event_id: &str) -> anyhow::Result<Option<String>> {
+        let known = self.messages.find(|message| {
+            message.event_id == event_id || message.image_event_ids.iter().any(|id| id == event_id)
+        });
+        if known.is_some() {
+            return Ok(known);
+        }
+
+        let event: Value = self
+            .client
+            .get(self.room_endpoint(&["event", event_id])?)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        let review = &event["content"][REVIEW_FIELD];
+        let review_id = match review["id"].as_str() {
+            Some(review_id) => review_id.to_string(),
+            None => return Ok(None),
+        };
+
+        // Only the main message carries the rendered review, remember it so we can edit it
+        if let (Some(body), Some(formatted_body)) =
+            (review["body"].as_str(), review["formatted_body"].as_str())
+        {
+            // Missing for messages from before these were stored
+            let info = ReviewInfo {
+                dish: review["dish"].as_str().map(|dish| dish.to_string()),
+                location: review["location"]
+                    .as_str()
+                    .map(|location| location.to_string()),
+                required_approvals: review["required_approvals"]
+                    .as_u64()
+                    .map(|approvals| approvals as usize),
+            };
+            self.messages.insert(
+                &review_id,
+                info,
+                MatrixMessage {
+                    event_id: event_id.to_string(),
+                    image_event_ids: vec![],
+                    body: body.to_string(),
+                    formatted_body: formatted_body.to_string(),
+                },
+            );
+        }
+
+        Ok(Some(review_id))
+    }
+}
+
+#[async_trait]
+impl NotificationSink for MatrixSink {
+    fn name(&self) -> &str {
+        "matrix"
+    }
+
+    async fn deliver_review(&self, review: &Review) -> anyhow::Result<()> {
+        if self.messages.contains(&review.id.0) {
+            info!(
+                "Review {} already has a matrix message, skipping it",
+                review.id
+            );
+            return Ok(());
+        }
+
+        let (body, formatted_body) = render_review(&self.settings, review);
+        let info = ReviewInfo::new(&self.settings, review);
+        let status = get_status(
+            &ModerationState::Pending,
+            None,
+            self.moderator.can_approve(&info),
+        );
+        let event_id = self
+            .send_event(
+                "m.room.message",
+                &json!({
+                    "msgtype": "m.text",
+                    "body": format!("{}\n\n{}", body, status),
+                    "format": "org.matrix.custom.html",
+                    "formatted_body": format!("{}<p><em>{}</em></p>", formatted_body, escape_html(&status)),
+                    REVIEW_FIELD: {
+                        "id": review.id.0,
+                        "body": body,
+                        "formatted_body": formatted_body,
+                        "dish": review.occurrence.dish.name_de,
+                        "location": review.occurrence.location.name,
+                    },
+                }),
+            )
+            .await?;
+
+        let mut image_event_ids = vec![];
+        for image in &review.images {
+            match self.send_image(review, image).await {
+                Ok(event_id) => image_event_ids.push(event_id),
+                Err(err) => warn!(
+                    "Could not send image {} of review {} to matrix: {}",
+                    image.id, review.id, err
+                ),
+            }
+        }
+
+        self.messages.insert(
+            &review.id.0,
+            ReviewInfo::new(&self.settings, review),
+            MatrixMessage {
+                event_id,
+                image_event_ids,
+                body,
+                formatted_body,
+            },
+        );
+
+        Ok(())
+    }
+
+    async fn update_review_state(&self, update: &ReviewUpdate) -> anyhow::Result<()> {
+        let message = match update {
+            ReviewUpdate::Accepted(review) => self
+                .messages
+                .set_external_state(&review.id.0, ModerationState::Approved),
+            ReviewUpdate::Unapproved(review_id) => self
+                .messages
+                .set_external_state(&review_id.0, ModerationState::Unapproved),
+            ReviewUpdate::Rejected(review_id) => self
+                .messages
+                .set_external_state(&review_id.0, ModerationState::Rejected),
+            ReviewUpdate::Deleted(review_id) => self
+                .messages
+                .set_external_state(&review_id.0, ModerationState::Deleted),
+            ReviewUpdate::Edited(review) => {
+                let (body, formatted_body) = render_review(&self.settings, review);
+                self.messages.edit(&review.id.0, |message| {
+                    message.body = body;
+                    message.formatted_body = formatted_body;
+                })
+            }
+        };
+
+        match message {
+            Some(message) => self.edit_message(&message).await,
+            None => Ok(()),
+        }
+    }
+}
+
+fn endpoint(homeserver_url: &str, segments: &[&str]) -> anyhow::Result<Url> {
+    let mut url = Url::parse(homeserver_url)?;
+    url.path_segments_mut()
+        .map_err(|_| anyhow::anyhow!("Invalid homeserver url: {}", homeserver_url))?
+        .pop_if_empty()
+        .extend(segments);
+    Ok(url)
+}
+
+/// Plain text and HTML version of a review, laid out like the discord embed
+fn render_review(settings: &Settings, review: &Review) -> (String, String) {
+    let title = get_title(review);
+    let url = get_url(settings, review);
+    let author = get_author(review);
+
+    let mut body = format!("{}\n{}\nby {}", title, url, author);
+    let mut formatted_body = format!(
+        "<h4><a href=\"{}\">{}</a></h4><p>by {}</p>",
+        escape_html(&url),
+        escape_html(&title),
+        escape_html(&author)
+    );
+
+    if let Some(text) = review.text.as_ref().filter(|text| !text.is_empty()) {
+        body.push_str(&format!("\n\n{}", text));
+        formatted_body.push_str(&format!(
+            "<p>{}</p>",
+            escape_html(text).replace('\n', "<br>")
+        ));
+    }
+
+    (body, formatted_body)
+}
+
+fn get_status(state: &ModerationState, who: Option<&str>, can_approve: bool) -> String {
+    match state {
+        ModerationState::Pending | ModerationState::Unapproved | ModerationState::Rejected => {
+            if can_approve {
+                format!(
+                    "React with {} to approve or {} to delete",
+                    APPROVE_KEY, DELETE_KEY
+                )
+            } else {
+                format!("React with {} to delete", DELETE_KEY)
+            }
+        }
+        ModerationState::Approved | ModerationState::Deleted => render::get_status(state, who),
+    }
+}
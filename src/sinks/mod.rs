@@ -0,0 +1,163 @@
+use crate::events::ReviewEvent;
+use crate::gql::client::MensattGqlClient;
+use crate::gql::{Review, Uuid};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use log::{info, warn};
+use std::collections::HashSet;
+use std::sync::Arc;
+use tokio::sync::broadcast;
+
+pub mod email;
+pub mod matrix;
+pub mod moderation;
+pub mod mqtt;
+pub mod payload;
+pub mod push;
+pub mod render;
+pub mod slack;
+pub mod stream;
+pub mod telegram;
+pub mod webhook;
+
+/// A change to a review that was already delivered
+#[derive(Debug, Clone)]
+pub enum ReviewUpdate {
+    Accepted(Review),
+    Edited(Review),
+    // Made by moderators through one of our sinks
+    Unapproved(Uuid),
+    Rejected(Uuid),
+    Deleted(Uuid),
+}
+
+/// Somewhere new reviews are sent to, e.g. a chat for moderators
+#[async_trait]
+pub trait NotificationSink: Send + Sync {
+    /// Short name used in logs
+    fn name(&self) -> &str;
+
+    /// Sends a new review, the listener may report a review twice when it just reconnected
+    async fn deliver_review(&self, review: &Review) -> anyhow::Result<()>;
+
+    async fn update_review_state(&self, update: &ReviewUpdate) -> anyhow::Result<()>;
+}
+
+/// Feeds all review events into a sink until the event bus is closed.
+///
+/// Every sink runs in its own task with its own receiver, so a slow or failing sink does not hold
+/// up the others. A sink falling behind is sent the unapproved reviews it missed from the backend,
+/// other events it skipped (approvals, edits, deletions) are lost.
+pub async fn forward_events(
+    sink: Arc<dyn NotificationSink>,
+    mut rx: broadcast::Receiver<ReviewEvent>,
+    gql_client: Arc<MensattGqlClient>,
+) {
+    // Creation time of the newest review delivered, the reviews skipped when falling behind are newer.
+    // Older reviews were created before the sink started, so they weren't missed by it.
+    let mut last_created = Utc::now();
+    // Reviews sent when catching up, which may still be waiting in the receiver
+    let mut resent = HashSet::new();
+
+    loop {
+        let event = match rx.recv().await {
+            Ok(event) => event,
+            Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                warn!(
+                    "Sink {} fell behind on review events, skipped {} of them",
+                    sink.name(),
+                    skipped
+                );
+                resent = catch_up(sink.as_ref(), &gql_client, &mut last_created).await;
+                continue;
+            }
+            Err(broadcast::error::RecvError::Closed) => break,
+        };
+
+        let result = match event {
+            ReviewEvent::Created(review) => {
+                if resent.remove(&review.id.0) {
+                    continue;
+                }
+                if let Ok(created_at) = review.created_at.parse() {
+                    last_created = last_created.max(created_at);
+                }
+                sink.deliver_review(&review).await
+            }
+            ReviewEvent::Accepted(review) => {
+                sink.update_review_state(&ReviewUpdate::Accepted(review))
+                    .await
+            }
+            ReviewEvent::Updated(review) => {
+                sink.update_review_state(&ReviewUpdate::Edited(review))
+                    .await
+            }
+            ReviewEvent::Unapproved(id) => {
+                sink.update_review_state(&ReviewUpdate::Unapproved(id))
+                    .await
+            }
+            ReviewEvent::Rejected(id) => {
+                sink.update_review_state(&ReviewUpdate::Rejected(id)).await
+            }
+            ReviewEvent::Deleted(id) => sink.update_review_state(&ReviewUpdate::Deleted(id)).await,
+            ReviewEvent::ListenerConnected | ReviewEvent::ListenerDisconnected => Ok(()),
+        };
+
+        if let Err(err) = result {
+            warn!(
+                "Sink {} failed to handle review event: {}",
+                sink.name(),
+                err
+            );
+        }
+    }
+
+    info!("Review events closed, stopping sink {}", sink.name());
+}
+
+/// Delivers the unapproved reviews created after `last_created` and returns their ids
+async fn catch_up(
+    sink: &dyn NotificationSink,
+    gql_client: &MensattGqlClient,
+    last_created: &mut DateTime<Utc>,
+) -> HashSet<String> {
+    let reviews = match gql_client.get_unapproved_reviews().await {
+        Ok(reviews) => reviews,
+        Err(err) => {
+            warn!(
+                "Could not get unapproved reviews, sink {} may miss some: {}",
+                sink.name(),
+                err
+            );
+            return HashSet::new();
+        }
+    };
+
+    let mut missed = vec![];
+    for review in reviews {
+        match review.created_at.parse() {
+            Ok(created_at) if created_at > *last_created => missed.push((created_at, review)),
+            Ok(_) => {}
+            Err(err) => warn!(
+                "Could not parse creation time of review {}, skipping it: {}",
+                review.id, err
+            ),
+        }
+    }
+    missed.sort_by_key(|(created_at, _)| *created_at);
+    info!(
+        "Sending {} reviews the {} sink may have missed",
+        missed.len(),
+        sink.name()
+    );
+
+    let mut resent = HashSet::new();
+    for (created_at, review) in missed {
+        if let Err(err) = sink.deliver_review(&review).await {
+            warn!("Sink {} failed to deliver review: {}", sink.name(), err);
+        }
+        *last_created = created_at;
+        resent.insert(review.id.0);
+    }
+    resent
+}
@@ -0,0 +1,279 @@
+use crate::audit::{AuditAction, AuditEntry, AuditLog};
+use crate::discord::bot::post_audit_entry;
+use crate::events::{EventBus, ReviewEvent};
+use crate::gql::client::MensattGqlClient;
+use crate::gql::{Review, Uuid};
+use crate::settings::Settings;
+use crate::store::{ModerationState, ReviewStore};
+use chrono::Utc;
+use log::warn;
+use serenity::all::Http;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+/// Message a review was posted with in a chat, `M` is whatever the platform needs to edit it
+#[derive(Clone)]
+pub struct ReviewMessage<M> {
+    pub content: M,
+    pub state: ModerationState,
+    // Who moderated the review, unknown for changes made elsewhere
+    pub who: Option<String>,
+    pub info: ReviewInfo,
+}
+
+/// What is known about a review besides its id, nothing for messages from before a restart
+#[derive(Clone, Default)]
+pub struct ReviewInfo {
+    pub dish: Option<String>,
+    pub location: Option<String>,
+    pub required_approvals: Option<usize>,
+}
+
+impl ReviewInfo {
+    pub fn new(settings: &Settings, review: &Review) -> Self {
+        Self {
+            dish: Some(review.occurrence.dish.name_de.clone()),
+            location: Some(review.occurrence.location.name.clone()),
+            required_approvals: Some(
+                settings
+                    .discord
+                    .get_required_approvals(review.stars, !review.images.is_empty()),
+            ),
+        }
+    }
+}
+
+/// Messages of a chat sink, keyed by review id
+///
+/// NOTE: This is only kept in memory, so reviews posted before a restart can still be moderated,
+/// but won't reflect changes made elsewhere
+pub struct Messages<M> {
+    messages: Mutex<HashMap<String, ReviewMessage<M>>>,
+}
+
+impl<M> Default for Messages<M> {
+    fn default() -> Self {
+        Self {
+            messages: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl<M: Clone> Messages<M> {
+    /// Whether the review was posted already
+    pub fn contains(&self, review_id: &str) -> bool {
+        self.messages.lock().unwrap().contains_key(review_id)
+    }
+
+    /// Remembers the message of a pending review, unless the review already has one
+    pub fn insert(&self, review_id: &str, info: ReviewInfo, content: M) {
+        self.messages
+            .lock()
+            .unwrap()
+            .entry(review_id.to_string())
+            .or_insert(ReviewMessage {
+                content,
+                state: ModerationState::Pending,
+                who: None,
+                info,
+            });
+    }
+
+    pub fn get(&self, review_id: &str) -> Option<ReviewMessage<M>> {
+        self.messages.lock().unwrap().get(review_id).cloned()
+    }
+
+    pub fn get_state(&self, review_id: &str) -> Option<ModerationState> {
+        self.messages
+            .lock()
+            .unwrap()
+            .get(review_id)
+            .map(|message| message.state)
+    }
+
+    /// The review whose message matches
+    pub fn find(&self, matches: impl Fn(&M) -> bool) -> Option<String> {
+        self.messages
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|(_, message)| matches(&message.content))
+            .map(|(review_id, _)| review_id.clone())
+    }
+
+    /// Changes the content of a known message and returns it as it is now
+    pub fn edit(&self, review_id: &str, edit: impl FnOnce(&mut M)) -> Option<ReviewMessage<M>> {
+        let mut messages = self.messages.lock().unwrap();
+        let message = messages.get_mut(review_id)?;
+        edit(&mut message.content);
+        Some(message.clone())
+    }
+
+    /// Updates the state of a known message and returns it as it was before
+    pub fn set_state(
+        &self,
+        review_id: &str,
+        state: ModerationState,
+        who: Option<String>,
+    ) -> Option<ReviewMessage<M>> {
+        let mut messages = self.messages.lock().unwrap();
+        let message = messages.get_mut(review_id)?;
+        let previous = message.clone();
+        message.state = state;
+        message.who = who;
+        Some(previous)
+    }
+
+    /// Applies a change made elsewhere, returns the message if it has to be edited to show it
+    pub fn set_external_state(
+        &self,
+        review_id: &str,
+        state: ModerationState,
+    ) -> Option<ReviewMessage<M>> {
+        let mut messages = self.messages.lock().unwrap();
+        let message = messages.get_mut(review_id)?;
+        // Changes made through our own buttons are reported as well, those are already displayed
+        if message.state == state {
+            return None;
+        }
+        message.state = state;
+        message.who = None;
+        Some(message.clone())
+    }
+}
+
+/// Carries out the decisions moderators make in the chat sinks, shared by all of them
+pub struct Moderator {
+    gql_client: MensattGqlClient,
+    events: EventBus,
+    audit_log: Arc<AuditLog>,
+    // Decisions are posted to the discord audit channel as well
+    http: Http,
+    audit_channel: Option<u64>,
+    // Knows the location of reviews posted to discord, in case the message of the sink is unknown
+    store: Arc<ReviewStore>,
+    // Votes are only collected by the discord bot, so reviews whose rules are unknown can only be
+    // approved here if there is no quorum at all
+    can_approve_unknown: bool,
+}
+
+impl Moderator {
+    pub fn new(
+        settings: Settings,
+        events: EventBus,
+        audit_log: Arc<AuditLog>,
+        store: Arc<ReviewStore>,
+    ) -> Self {
+        Self {
+            can_approve_unknown: !settings.discord.has_quorum(),
+            http: Http::new(&settings.discord.token),
+            audit_channel: settings.audit.get_channel(),
+            gql_client: MensattGqlClient::new(settings),
+            events,
+            audit_log,
+            store,
+        }
+    }
+
+    /// Whether approving the review is offered, which isn't the case if it needs a quorum
+    pub fn can_approve(&self, info: &ReviewInfo) -> bool {
+        match info.required_approvals {
+            Some(required_approvals) => required_approvals <= 1,
+            None => self.can_approve_unknown,
+        }
+    }
+
+    /// Approves, unapproves, rejects or deletes the review, remembering the new state in `messages`
+    pub async fn moderate<M: Clone>(
+        &self,
+        sink: &str,
+        messages: &Messages<M>,
+        review_id: &str,
+        state: ModerationState,
+        who: &str,
+    ) -> anyhow::Result<()> {
+        let info = messages
+            .get(review_id)
+            .map(|message| message.info)
+            .unwrap_or_default();
+        if state == ModerationState::Approved && !self.can_approve(&info) {
+            anyhow::bail!("The review may need several approvals, which only discord collects");
+        }
+
+        // Stored before updating, as the backend reports our own changes back to us and we must not
+        // mistake them for external ones
+        let previous = messages.set_state(review_id, state, Some(who.to_string()));
+
+        let id = Uuid(review_id.to_string());
+        let result = match state {
+            ModerationState::Approved => self.gql_client.update_review(id, true).await,
+            ModerationState::Unapproved | ModerationState::Rejected => {
+                self.gql_client.update_review(id, false).await
+            }
+            ModerationState::Deleted => self.gql_client.delete_review(id).await,
+            ModerationState::Pending => Err(anyhow::anyhow!("Reviews can't be made pending")),
+        };
+        if let Err(err) = result {
+            if let Some(previous) = previous {
+                messages.set_state(review_id, previous.state, previous.who);
+            }
+            return Err(err);
+        }
+
+        let (prior_state, info) = match previous {
+            Some(previous) => (Some(previous.state), previous.info),
+            // The message is not known after a restart, so neither is the review
+            None => (None, ReviewInfo::default()),
+        };
+        self.audit(sink, who, state, review_id, prior_state, info)
+            .await;
+
+        // The backend only reports approvals, everything else is passed on to the other sinks here
+        let id = Uuid(review_id.to_string());
+        match state {
+            ModerationState::Unapproved => self.events.publish(ReviewEvent::Unapproved(id)),
+            ModerationState::Rejected => self.events.publish(ReviewEvent::Rejected(id)),
+            ModerationState::Deleted => self.events.publish(ReviewEvent::Deleted(id)),
+            ModerationState::Pending | ModerationState::Approved => {}
+        }
+
+        Ok(())
+    }
+
+    async fn audit(
+        &self,
+        sink: &str,
+        who: &str,
+        state: ModerationState,
+        review_id: &str,
+        prior_state: Option<ModerationState>,
+        info: ReviewInfo,
+    ) {
+        let action = match state {
+            ModerationState::Approved => AuditAction::Approve,
+            ModerationState::Unapproved => AuditAction::Unapprove,
+            ModerationState::Rejected => AuditAction::Reject,
+            ModerationState::Deleted => AuditAction::Delete,
+            ModerationState::Pending => return,
+        };
+
+        let entry = AuditEntry {
+            timestamp: Utc::now(),
+            sink: sink.to_string(),
+            user_id: None,
+            user_name: who.to_string(),
+            action,
+            review_id: review_id.to_string(),
+            dish: info.dish,
+            location: info
+                .location
+                .or_else(|| self.store.get(review_id).and_then(|record| record.location)),
+            reason: None,
+            prior_state,
+        };
+        if let Err(err) = self.audit_log.append(&entry) {
+            warn!("Could not write audit log entry {:?}: {}", entry, err);
+        }
+        post_audit_entry(&self.http, self.audit_channel, &entry).await;
+    }
+}
@@ -0,0 +1,116 @@
+use crate::gql::Review;
+use crate::settings::{Mqtt, Settings};
+use crate::sinks::payload::EventPayload;
+use crate::sinks::{NotificationSink, ReviewUpdate};
+use async_trait::async_trait;
+use log::{debug, info, warn};
+use rumqttc::{AsyncClient, EventLoop, MqttOptions, QoS, TlsConfiguration, Transport};
+use rustls::{ClientConfig, RootCertStore};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Publishes every review event as JSON to an MQTT broker.
+///
+/// Each kind of event has its own topic below the configured prefix, e.g. `mensatt/reviews/created`,
+/// the payload is the same as the one of the webhook sink.
+pub struct MqttSink {
+    settings: Settings,
+    mqtt: Mqtt,
+    client: AsyncClient,
+    qos: QoS,
+}
+
+impl MqttSink {
+    pub fn start(settings: Settings) -> anyhow::Result<Self> {
+        let mqtt = settings.mqtt.clone().ok_or_else(|| {
+            anyhow::anyhow!("MQTT sink is enabled, but there is no [mqtt] section")
+        })?;
+        let qos = rumqttc::qos(mqtt.qos)
+            .map_err(|_| anyhow::anyhow!("Invalid MQTT QoS {}, must be 0, 1 or 2", mqtt.qos))?;
+
+        let mut options = MqttOptions::new(&mqtt.client_id, &mqtt.host, mqtt.port);
+        options.set_keep_alive(Duration::from_secs(30));
+        if let Some(username) = &mqtt.username {
+            options.set_credentials(username, mqtt.password.clone().unwrap_or_default());
+        }
+        if mqtt.tls {
+            // The native certificates are missing in the docker image, so bring our own
+            let roots = RootCertStore {
+                roots: webpki_roots::TLS_SERVER_ROOTS.to_vec(),
+            };
+            let config = ClientConfig::builder()
+                .with_root_certificates(roots)
+                .with_no_client_auth();
+            options.set_transport(Transport::tls_with_config(TlsConfiguration::Rustls(
+                Arc::new(config),
+            )));
+        }
+
+        // Publishing only waits for the event loop if this many messages are queued up
+        let (client, event_loop) = AsyncClient::new(options, 32);
+        tokio::spawn(drive_event_loop(event_loop));
+
+        Ok(Self {
+            settings,
+            mqtt,
+            client,
+            qos,
+        })
+    }
+
+    async fn publish(&self, payload: &EventPayload) -> anyhow::Result<()> {
+        let topic = format!(
+            "{}/{}",
+            self.mqtt.topic_prefix.trim_end_matches('/'),
+            payload.event
+        );
+        self.client
+            .publish(
+                &topic,
+                self.qos,
+                self.mqtt.retain,
+                serde_json::to_vec(payload)?,
+            )
+            .await?;
+        debug!("Published review {} to {}", payload.review_id, topic);
+        Ok(())
+    }
+}
+
+/// Keeps the connection to the broker alive, the client does nothing without this
+async fn drive_event_loop(mut event_loop: EventLoop) {
+    let mut connected = false;
+    loop {
+        match event_loop.poll().await {
+            Ok(_) => {
+                if !connected {
+                    info!("Connected to MQTT broker");
+                    connected = true;
+                }
+            }
+            Err(err) => {
+                // Polling again reconnects, queued messages are sent once that succeeds
+                warn!("MQTT connection failed, trying again in 5 seconds: {}", err);
+                connected = false;
+                tokio::time::sleep(Duration::from_secs(5)).await;
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl NotificationSink for MqttSink {
+    fn name(&self) -> &str {
+        "mqtt"
+    }
+
+    async fn deliver_review(&self, review: &Review) -> anyhow::Result<()> {
+        self.publish(&EventPayload::created(&self.settings, review))
+            .await
+    }
+
+    async fn update_review_state(&self, update: &ReviewUpdate) -> anyhow::Result<()> {
+        self.publish(&EventPayload::updated(&self.settings, update))
+            .await
+    }
+}
@@ -0,0 +1,89 @@
+use crate::gql::Review;
+use crate::settings::Settings;
+use crate::sinks::render::get_url;
+use crate::sinks::ReviewUpdate;
+use serde::Serialize;
+
+/// JSON representation of a review for consumers outside of this service
+#[derive(Debug, Clone, Serialize)]
+pub struct ReviewPayload {
+    pub id: String,
+    pub dish: String,
+    pub display_name: Option<String>,
+    pub stars: i32,
+    pub text: Option<String>,
+    pub created_at: String,
+    pub image_urls: Vec<String>,
+    pub occurrence_url: String,
+}
+
+impl ReviewPayload {
+    pub fn new(settings: &Settings, review: &Review) -> Self {
+        Self {
+            id: review.id.0.clone(),
+            dish: review.occurrence.dish.name_de.clone(),
+            display_name: review.display_name.clone(),
+            stars: review.stars,
+            text: review.text.clone(),
+            created_at: review.created_at.0.clone(),
+            // NOTE: These require authentication with the image service, which is up to the
+            // consumer, as we must not hand out our own key
+            image_urls: review
+                .images
+                .iter()
+                .map(|image| format!("{}{}", settings.image.image_url, image.id.0))
+                .collect(),
+            occurrence_url: get_url(settings, review),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EventPayload {
+    /// One of `created`, `accepted`, `edited`, `unapproved`, `rejected` or `deleted`
+    pub event: &'static str,
+    pub review_id: String,
+    /// Missing for unapproved, rejected and deleted reviews
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub review: Option<ReviewPayload>,
+}
+
+impl EventPayload {
+    pub fn created(settings: &Settings, review: &Review) -> Self {
+        Self {
+            event: "created",
+            review_id: review.id.0.clone(),
+            review: Some(ReviewPayload::new(settings, review)),
+        }
+    }
+
+    pub fn updated(settings: &Settings, update: &ReviewUpdate) -> Self {
+        match update {
+            ReviewUpdate::Accepted(review) => Self {
+                event: "accepted",
+                review_id: review.id.0.clone(),
+                review: Some(ReviewPayload::new(settings, review)),
+            },
+            ReviewUpdate::Edited(review) => Self {
+                event: "edited",
+                review_id: review.id.0.clone(),
+                review: Some(ReviewPayload::new(settings, review)),
+            },
+            ReviewUpdate::Unapproved(review_id) => Self {
+                event: "unapproved",
+                review_id: review_id.0.clone(),
+                review: None,
+            },
+            ReviewUpdate::Rejected(review_id) => Self {
+                event: "rejected",
+                review_id: review_id.0.clone(),
+                review: None,
+            },
+            ReviewUpdate::Deleted(review_id) => Self {
+                event: "deleted",
+                review_id: review_id.0.clone(),
+                review: None,
+            },
+        }
+    }
+}
@@ -0,0 +1,151 @@
+use crate::gql::Review;
+use crate::settings::{Push, PushService, Settings};
+use crate::sinks::render::{get_title, get_url};
+use crate::sinks::{NotificationSink, ReviewUpdate};
+use async_trait::async_trait;
+use log::info;
+use serde_json::json;
+use std::collections::HashSet;
+use std::sync::Mutex;
+
+/// Sends a push notification through ntfy or gotify for every new review.
+///
+/// This is meant for moderators who only want to know that something is waiting for them, so
+/// only new reviews are sent and there is no way to moderate from here.
+pub struct PushSink {
+    settings: Settings,
+    push: Push,
+    client: reqwest::Client,
+    // Reviews a notification was sent for, kept in memory only
+    sent: Mutex<HashSet<String>>,
+}
+
+impl PushSink {
+    pub fn new(settings: Settings) -> anyhow::Result<Self> {
+        let push = settings.push.clone().ok_or_else(|| {
+            anyhow::anyhow!("Push sink is enabled, but there is no [push] section")
+        })?;
+        if push.service == PushService::Ntfy && push.topic.is_none() {
+            anyhow::bail!("Push sink uses ntfy, but there is no topic");
+        }
+
+        Ok(Self {
+            settings,
+            push,
+            client: reqwest::Client::new(),
+            sent: Mutex::new(HashSet::new()),
+        })
+    }
+
+    /// Link to the review on mensatt
+    ///
+    /// The discord message is posted at the same time as the push is sent, so there is no link
+    /// to it yet.
+    fn get_click_url(&self, review: &Review) -> String {
+        get_url(&self.settings, review)
+    }
+
+    async fn send_ntfy(
+        &self,
+        title: &str,
+        message: &str,
+        priority: u8,
+        click: &str,
+    ) -> anyhow::Result<()> {
+        let mut request = self.client.post(&self.push.server_url).json(&json!({
+            "topic": self.push.topic,
+            "title": title,
+            "message": message,
+            "priority": priority,
+            "click": click,
+        }));
+        if let Some(token) = &self.push.token {
+            request = request.bearer_auth(token);
+        }
+
+        request.send().await?.error_for_status()?;
+        Ok(())
+    }
+
+    async fn send_gotify(
+        &self,
+        title: &str,
+        message: &str,
+        priority: u8,
+        click: &str,
+    ) -> anyhow::Result<()> {
+        let mut request = self
+            .client
+            .post(format!(
+                "{}/message",
+                self.push.server_url.trim_end_matches('/')
+            ))
+            .json(&json!({
+                "title": title,
+                "message": message,
+                // Gotify goes from 0 to 10 instead of 1 to 5
+                "priority": priority * 2,
+                "extras": {
+                    "client::notification": { "click": { "url": click } },
+                },
+            }));
+        if let Some(token) = &self.push.token {
+            request = request.header("X-Gotify-Key", token);
+        }
+
+        request.send().await?.error_for_status()?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl NotificationSink for PushSink {
+    fn name(&self) -> &str {
+        "push"
+    }
+
+    async fn deliver_review(&self, review: &Review) -> anyhow::Result<()> {
+        if !self.sent.lock().unwrap().insert(review.id.0.clone()) {
+            info!("Already sent a push notification for review {}", review.id);
+            return Ok(());
+        }
+
+        let title = get_title(review);
+        let message = review
+            .text
+            .clone()
+            .filter(|text| !text.is_empty())
+            .unwrap_or("(no text)".to_string());
+        let priority = get_priority(review.stars);
+        let click = self.get_click_url(review);
+
+        let result = match self.push.service {
+            PushService::Ntfy => self.send_ntfy(&title, &message, priority, &click).await,
+            PushService::Gotify => self.send_gotify(&title, &message, priority, &click).await,
+        };
+        if let Err(err) = result {
+            // Allows another attempt, should the review be reported again
+            self.sent.lock().unwrap().remove(&review.id.0);
+            return Err(err);
+        }
+
+        info!("Sent push notification for review {}", review.id);
+        Ok(())
+    }
+
+    async fn update_review_state(&self, _update: &ReviewUpdate) -> anyhow::Result<()> {
+        // Nobody needs to be woken up for a review that was already handled
+        Ok(())
+    }
+}
+
+/// Priority on ntfy's scale from 1 (min) to 5 (max)
+///
+/// Bad reviews are more likely to contain something that should not go online, so they come first.
+fn get_priority(stars: i32) -> u8 {
+    match stars {
+        ..=2 => 4,
+        3 => 3,
+        _ => 2,
+    }
+}
@@ -0,0 +1,63 @@
+use crate::gql::Review;
+use crate::settings::Settings;
+use crate::store::ModerationState;
+
+/// Title of a review, laid out like the title of the discord embed
+pub fn get_title(review: &Review) -> String {
+    format!(
+        "{} | {}",
+        review.occurrence.dish.name_de,
+        get_stars(review.stars)
+    )
+}
+
+pub fn get_stars(stars: i32) -> String {
+    (0..stars).map(|_| '★').collect()
+}
+
+/// Link to the occurrence the review belongs to on mensatt
+pub fn get_url(settings: &Settings, review: &Review) -> String {
+    format!(
+        "{}{}",
+        settings.mensatt.occurrence_url, review.occurrence.id.0
+    )
+}
+
+pub fn get_author(review: &Review) -> String {
+    review
+        .display_name
+        .clone()
+        .filter(|name| !name.is_empty())
+        .unwrap_or("Anonymous".to_string())
+}
+
+/// Cuts the text off after `max_len` characters, marking that something is missing
+pub fn shorten(text: &str, max_len: usize) -> String {
+    match text.char_indices().nth(max_len) {
+        Some((end, _)) => format!("{}…", &text[..end]),
+        None => text.to_string(),
+    }
+}
+
+/// Status line below a review, telling who moderated it
+pub fn get_status(state: &ModerationState, who: Option<&str>) -> String {
+    // Without a user, the action was taken somewhere else (e.g. discord or the admin panel)
+    let by = who
+        .map(|who| format!("by {}", who))
+        .unwrap_or("externally".to_string());
+
+    match state {
+        ModerationState::Pending => "Waiting for moderation".to_string(),
+        ModerationState::Approved => format!("✅ Approved {}", by),
+        ModerationState::Unapproved => format!("Unapproved {}", by),
+        ModerationState::Rejected => format!("🗑 Rejected {}", by),
+        ModerationState::Deleted => format!("🗑 Deleted {}", by),
+    }
+}
+
+pub fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -0,0 +1,655 @@
+use crate::gql::{Image, Review};
+use crate::image::ImageClient;
+use crate::settings::{Settings, Slack};
+use crate::sinks::moderation::{Messages, Moderator, ReviewInfo, ReviewMessage};
+use crate::sinks::render::{get_author, get_status, get_title, get_url, shorten};
+use crate::sinks::{NotificationSink, ReviewUpdate};
+use crate::store::ModerationState;
+use async_trait::async_trait;
+use axum::body::Bytes;
+use axum::extract::State;
+use axum::http::{HeaderMap, StatusCode};
+use axum::routing::post;
+use axum::Router;
+use hmac::{Hmac, Mac};
+use log::{debug, info, warn};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use sha2::Sha256;
+use std::sync::Arc;
+
+// Section texts are limited to 3000 characters
+const MAX_TEXT_LENGTH: usize = 2900;
+// Requests older than this are rejected, so that a captured request can't be replayed later on
+const MAX_REQUEST_AGE_SECS: i64 = 5 * 60;
+
+// Blocks we add below the rendered review, which are replaced whenever the state changes
+const STATUS_BLOCK_ID: &str = "status";
+const ACTIONS_BLOCK_ID: &str = "actions";
+
+#[derive(Clone)]
+struct SlackMessage {
+    channel: String,
+    ts: String,
+    // The rendered review, without the status and actions
+    blocks: Vec<Value>,
+}
+
+#[derive(Deserialize)]
+struct InteractionForm {
+    payload: String,
+}
+
+#[derive(Deserialize)]
+struct InteractionPayload {
+    #[serde(rename = "type")]
+    kind: String,
+    user: SlackUser,
+    channel: Option<SlackChannel>,
+    message: Option<InteractionMessage>,
+    #[serde(default)]
+    actions: Vec<BlockAction>,
+}
+
+#[derive(Deserialize)]
+struct SlackUser {
+    id: String,
+}
+
+#[derive(Deserialize)]
+struct SlackChannel {
+    id: String,
+}
+
+#[derive(Deserialize)]
+struct InteractionMessage {
+    ts: String,
+    #[serde(default)]
+    blocks: Vec<Value>,
+}
+
+#[derive(Deserialize)]
+struct BlockAction {
+    action_id: String,
+    value: Option<String>,
+}
+
+/// Posts new reviews into a Slack channel as Block Kit messages.
+///
+/// Moderation works through buttons on the message, for which slack sends interactivity requests
+/// to a small HTTP server we run.
+pub struct SlackSink {
+    settings: Settings,
+    slack: Slack,
+    client: reqwest::Client,
+    moderator: Arc<Moderator>,
+    image_client: ImageClient,
+    messages: Messages<SlackMessage>,
+}
+
+impl SlackSink {
+    pub async fn start(settings: Settings, moderator: Arc<Moderator>) -> anyhow::Result<Arc<Self>> {
+        let slack = settings.slack.clone().ok_or_else(|| {
+            anyhow::anyhow!("Slack sink is enabled, but there is no [slack] section")
+        })?;
+
+        let mut default_headers = reqwest::header::HeaderMap::new();
+        default_headers.insert(
+            "Authorization",
+            format!("Bearer {}", slack.bot_token).parse()?,
+        );
+        let client = reqwest::Client::builder()
+            .default_headers(default_headers)
+            .build()?;
+
+        let listener = tokio::net::TcpListener::bind(&slack.listen_addr).await?;
+        info!(
+            "Listening for slack interactivity requests on {}",
+            slack.listen_addr
+        );
+
+        let sink = Arc::new(Self {
+            moderator,
+            image_client: ImageClient::new(settings.clone()),
+            settings,
+            slack,
+            client,
+            messages: Messages::default(),
+        });
+
+        let auth = sink.call("auth.test", &json!({})).await?;
+        info!(
+            "Logged in to slack as {}",
+            auth["user"].as_str().unwrap_or_default()
+        );
+        if sink.slack.moderators.is_empty() {
+            warn!("No slack moderators are configured, the buttons of reviews won't do anything");
+        }
+
+        let app = Router::new()
+            .route("/slack/actions", post(handle_interaction))
+            .with_state(sink.clone());
+        tokio::spawn(async move {
+            if let Err(err) = axum::serve(listener, app).await {
+                warn!("Slack interactivity server stopped: {}", err);
+            }
+        });
+
+        Ok(sink)
+    }
+
+    async fn call(&self, method: &str, body: &Value) -> anyhow::Result<Value> {
+        let resp: Value = self
+            .client
+            .post(format!(
+                "{}/{}",
+                self.slack.api_url.trim_end_matches('/'),
+                method
+            ))
+            .json(body)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        // Errors are reported in the body, the status code is 200 either way
+        if resp["ok"].as_bool() != Some(true) {
+            anyhow::bail!(
+                "Slack {} failed: {}",
+                method,
+                resp["error"].as_str().unwrap_or_default()
+            );
+        }
+        Ok(resp)
+    }
+
+    /// Uploads an image into the thread of the review's message.
+    ///
+    /// Image blocks need a public url, which we can't hand out without our key.
+    async fn upload_image(&self, image: &Image, thread_ts: &str) -> anyhow::Result<()> {
+        let (data, _) = self.image_client.download_image(&image.id.0).await?;
+
+        let upload: Value = self
+            .client
+            .post(format!(
+                "{}/files.getUploadURLExternal",
+                self.slack.api_url.trim_end_matches('/')
+            ))
+            .form(&[
+                ("filename", image.id.0.clone()),
+                ("length", data.len().to_string()),
+            ])
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        let (upload_url, file_id) =
+            match (upload["upload_url"].as_str(), upload["file_id"].as_str()) {
+                (Some(upload_url), Some(file_id)) => (upload_url, file_id),
+                _ => anyhow::bail!(
+                    "Slack did not return an upload url: {}",
+                    upload["error"].as_str().unwrap_or_default()
+                ),
+            };
+
+        self.client
+            .post(upload_url)
+            .body(data)
+            .send()
+            .await?
+            .error_for_status()?;
+
+        self.call(
+            "files.completeUploadExternal",
+            &json!({
+                "files": [{ "id": file_id, "title": image.id.0 }],
+                "channel_id": self.slack.channel,
+                "thread_ts": thread_ts,
+            }),
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    async fn update_message(
+        &self,
+        message: &ReviewMessage<SlackMessage>,
+        review_id: &str,
+    ) -> anyhow::Result<()> {
+        self.call(
+            "chat.update",
+            &json!({
+                "channel": message.content.channel,
+                "ts": message.content.ts,
+                "text": get_status(&message.state, message.who.as_deref()),
+                "blocks": get_blocks(&message.content.blocks, &message.state, message.who.as_deref(), review_id, self.moderator.can_approve(&message.info)),
+            }),
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn handle_payload(&self, payload: InteractionPayload) -> anyhow::Result<()> {
+        if payload.kind != "block_actions" {
+            debug!("Ignoring slack interaction of type {}", payload.kind);
+            return Ok(());
+        }
+
+        let (channel, message) = match (payload.channel, payload.message) {
+            (Some(channel), Some(message)) if channel.id == self.slack.channel => {
+                (channel.id, message)
+            }
+            _ => {
+                debug!("Ignoring slack interaction from another channel");
+                return Ok(());
+            }
+        };
+
+        if !self.slack.moderators.contains(&payload.user.id) {
+            info!(
+                "Refused slack action of {}, who is no moderator",
+                payload.user.id
+            );
+            self.call(
+                "chat.postEphemeral",
+                &json!({
+                    "channel": channel,
+                    "user": payload.user.id,
+                    "text": "You are not allowed to moderate reviews",
+                }),
+            )
+            .await?;
+            return Ok(());
+        }
+
+        for action in payload.actions {
+            let review_id = match action.value {
+                Some(review_id) => review_id,
+                None => continue,
+            };
+            // Mentions are rendered as the user's name by slack
+            let who = format!("<@{}>", payload.user.id);
+
+            if let Err(err) = self
+                .handle_action(&action.action_id, &review_id, &who, &channel, &message)
+                .await
+            {
+                warn!(
+                    "Could not handle slack action {} on review {}: {}",
+                    action.action_id, review_id, err
+                );
+                match self
+                    .call(
+                        "chat.postEphemeral",
+                        &json!({
+                            "channel": channel,
+                            "user": payload.user.id,
+                            "text": format!("Could not {} the review, please try again", action.action_id),
+                        }),
+                    )
+                    .await
+                {
+                    Ok(_) => {}
+                    Err(err) => warn!("Could not tell slack user about failed action: {}", err),
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn handle_action(
+        &self,
+        action_id: &str,
+        review_id: &str,
+        who: &str,
+        channel: &str,
+        message: &InteractionMessage,
+    ) -> anyhow::Result<()> {
+        let state = match action_id {
+            "approve" => ModerationState::Approved,
+            "unapprove" => ModerationState::Unapproved,
+            "reject" => ModerationState::Rejected,
+            "delete" => ModerationState::Deleted,
+            _ => {
+                warn!("Received slack action with invalid id: {}", action_id);
+                return Ok(());
+            }
+        };
+        info!("{} pressed {} on review {}", who, action_id, review_id);
+
+        if self.messages.get_state(review_id) == Some(ModerationState::Deleted) {
+            debug!("Review {} is already deleted", review_id);
+            return Ok(());
+        }
+
+        self.moderator
+            .moderate(self.name(), &self.messages, review_id, state, who)
+            .await?;
+
+        // The blocks of the message are used, so that messages from before a restart work as well
+        self.update_message(
+            &ReviewMessage {
+                content: SlackMessage {
+                    channel: channel.to_string(),
+                    ts: message.ts.clone(),
+                    blocks: message
+                        .blocks
+                        .iter()
+                        .filter(|block| {
+                            let block_id = block["block_id"].as_str();
+                            block_id != Some(STATUS_BLOCK_ID) && block_id != Some(ACTIONS_BLOCK_ID)
+                        })
+                        .cloned()
+                        .collect(),
+                },
+                state,
+                who: Some(who.to_string()),
+                info: ReviewInfo::default(),
+            },
+            review_id,
+        )
+        .await
+    }
+}
+
+async fn handle_interaction(
+    State(sink): State<Arc<SlackSink>>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> StatusCode {
+    if let Err(err) = verify_signature(&sink.slack.signing_secret, &headers, &body) {
+        warn!("Rejecting slack interactivity request: {}", err);
+        return StatusCode::UNAUTHORIZED;
+    }
+
+    let payload = match serde_urlencoded::from_bytes::<InteractionForm>(&body)
+        .map_err(anyhow::Error::from)
+        .and_then(|form| Ok(serde_json::from_str::<InteractionPayload>(&form.payload)?))
+    {
+        Ok(payload) => payload,
+        Err(err) => {
+            warn!("Received invalid slack interactivity payload: {}", err);
+            return StatusCode::BAD_REQUEST;
+        }
+    };
+
+    // Slack expects an answer within 3 seconds, which the backend might not manage
+    tokio::spawn(async move {
+        if let Err(err) = sink.handle_payload(payload).await {
+            warn!("Could not handle slack interaction: {}", err);
+        }
+    });
+
+    StatusCode::OK
+}
+
+#[async_trait]
+impl NotificationSink for SlackSink {
+    fn name(&self) -> &str {
+        "slack"
+    }
+
+    async fn deliver_review(&self, review: &Review) -> anyhow::Result<()> {
+        if self.messages.contains(&review.id.0) {
+            info!(
+                "Review {} already has a slack message, skipping it",
+                review.id
+            );
+            return Ok(());
+        }
+
+        let blocks = render_review(&self.settings, review);
+        let resp = self
+            .call(
+                "chat.postMessage",
+                &json!({
+                    "channel": self.slack.channel,
+                    // Fallback for notifications
+                    "text": format!("New review for {}", review.occurrence.dish.name_de),
+                    "blocks": get_blocks(&blocks, &ModerationState::Pending, None, &review.id.0, self.moderator.can_approve(&ReviewInfo::new(&self.settings, review))),
+                }),
+            )
+            .await?;
+        let (channel, ts) = match (resp["channel"].as_str(), resp["ts"].as_str()) {
+            (Some(channel), Some(ts)) => (channel.to_string(), ts.to_string()),
+            _ => anyhow::bail!("Slack did not return the posted message"),
+        };
+
+        for image in &review.images {
+            match self.upload_image(image, &ts).await {
+                Ok(_) => {}
+                Err(err) => warn!(
+                    "Could not upload image {} of review {} to slack: {}",
+                    image.id, review.id, err
+                ),
+            }
+        }
+
+        self.messages.insert(
+            &review.id.0,
+            ReviewInfo::new(&self.settings, review),
+            SlackMessage {
+                channel,
+                ts,
+                blocks,
+            },
+        );
+
+        Ok(())
+    }
+
+    async fn update_review_state(&self, update: &ReviewUpdate) -> anyhow::Result<()> {
+        let (review_id, message) = match update {
+            ReviewUpdate::Accepted(review) => (
+                &review.id,
+                self.messages
+                    .set_external_state(&review.id.0, ModerationState::Approved),
+            ),
+            ReviewUpdate::Unapproved(review_id) => (
+                review_id,
+                self.messages
+                    .set_external_state(&review_id.0, ModerationState::Unapproved),
+            ),
+            ReviewUpdate::Rejected(review_id) => (
+                review_id,
+                self.messages
+                    .set_external_state(&review_id.0, ModerationState::Rejected),
+            ),
+            ReviewUpdate::Deleted(review_id) => (
+                review_id,
+                self.messages
+                    .set_external_state(&review_id.0, ModerationState::Deleted),
+            ),
+            ReviewUpdate::Edited(review) => {
+                let blocks = render_review(&self.settings, review);
+                (
+                    &review.id,
+                    self.messages
+                        .edit(&review.id.0, |message| message.blocks = blocks),
+                )
+            }
+        };
+
+        match message {
+            Some(message) => self.update_message(&message, &review_id.0).await,
+            None => Ok(()),
+        }
+    }
+}
+
+/// Checks the request signature as described in https://api.slack.com/authentication/verifying-requests-from-slack
+fn verify_signature(secret: &str, headers: &HeaderMap, body: &[u8]) -> anyhow::Result<()> {
+    let header = |name: &str| {
+        headers
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .ok_or_else(|| anyhow::anyhow!("Missing header {}", name))
+    };
+    let timestamp = header("X-Slack-Request-Timestamp")?;
+    let signature = header("X-Slack-Signature")?;
+
+    if (chrono::Utc::now().timestamp() - timestamp.parse::<i64>()?).abs() > MAX_REQUEST_AGE_SECS {
+        anyhow::bail!("Request timestamp {} is too old", timestamp);
+    }
+
+    let signature = signature
+        .strip_prefix("v0=")
+        .ok_or_else(|| anyhow::anyhow!("Unknown signature version"))?;
+
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes())?;
+    mac.update(format!("v0:{}:", timestamp).as_bytes());
+    mac.update(body);
+    // Compares in constant time
+    mac.verify_slice(&hex::decode(signature)?)
+        .map_err(|_| anyhow::anyhow!("Signature does not match"))
+}
+
+/// Blocks showing a review, laid out like the discord embed
+fn render_review(settings: &Settings, review: &Review) -> Vec<Value> {
+    let mut blocks = vec![json!({
+        "type": "section",
+        "text": {
+            "type": "mrkdwn",
+            "text": format!(
+                "*<{}|{}>*\nby {}",
+                get_url(settings, review),
+                escape_mrkdwn(&get_title(review)),
+                escape_mrkdwn(&get_author(review))
+            ),
+        },
+    })];
+
+    if let Some(text) = review.text.as_ref().filter(|text| !text.is_empty()) {
+        blocks.push(json!({
+            "type": "section",
+            "text": { "type": "plain_text", "text": shorten(text, MAX_TEXT_LENGTH) },
+        }));
+    }
+
+    if !review.images.is_empty() {
+        blocks.push(json!({
+            "type": "context",
+            "elements": [{
+                "type": "mrkdwn",
+                "text": format!("📷 {} image(s) in the thread", review.images.len()),
+            }],
+        }));
+    }
+
+    blocks
+}
+
+/// Review blocks followed by the status and the buttons, following `get_action_row` of the discord
+/// bot
+fn get_blocks(
+    review_blocks: &[Value],
+    state: &ModerationState,
+    who: Option<&str>,
+    review_id: &str,
+    can_approve: bool,
+) -> Vec<Value> {
+    let button = |text: &str, action_id: &str, style: Option<&str>| {
+        let mut button = json!({
+            "type": "button",
+            "text": { "type": "plain_text", "text": text, "emoji": true },
+            "action_id": action_id,
+            "value": review_id,
+        });
+        if let Some(style) = style {
+            button["style"] = json!(style);
+        }
+        button
+    };
+
+    let mut buttons = match state {
+        ModerationState::Pending | ModerationState::Unapproved => vec![
+            button("✅ Approve", "approve", Some("primary")),
+            button("🗑 Reject", "reject", Some("danger")),
+        ],
+        ModerationState::Approved => vec![button("🗑 Unapprove", "unapprove", None)],
+        ModerationState::Rejected => vec![
+            button("✅ Approve", "approve", Some("primary")),
+            button("🗑 Delete", "delete", Some("danger")),
+        ],
+        ModerationState::Deleted => vec![],
+    };
+    if !can_approve {
+        buttons.retain(|button| button["action_id"] != "approve");
+    }
+
+    let mut blocks = review_blocks.to_vec();
+    blocks.push(json!({
+        "type": "context",
+        "block_id": STATUS_BLOCK_ID,
+        "elements": [{ "type": "mrkdwn", "text": get_status(state, who) }],
+    }));
+    if !buttons.is_empty() {
+        blocks.push(json!({
+            "type": "actions",
+            "block_id": ACTIONS_BLOCK_ID,
+            "elements": buttons,
+        }));
+    }
+    blocks
+}
+
+fn escape_mrkdwn(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECRET: &str = "8f742231b10e8888abcd99yyyzzz85a5";
+    const BODY: &[u8] = b"payload=%7B%22type%22%3A%22block_actions%22%7D";
+
+    fn get_headers(secret: &str, timestamp: i64, body: &[u8]) -> HeaderMap {
+        let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).unwrap();
+        mac.update(format!("v0:{}:", timestamp).as_bytes());
+        mac.update(body);
+        let signature = format!("v0={}", hex::encode(mac.finalize().into_bytes()));
+
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "X-Slack-Request-Timestamp",
+            timestamp.to_string().parse().unwrap(),
+        );
+        headers.insert("X-Slack-Signature", signature.parse().unwrap());
+        headers
+    }
+
+    #[test]
+    fn verify_valid_signature() {
+        let headers = get_headers(SECRET, chrono::Utc::now().timestamp(), BODY);
+        assert!(verify_signature(SECRET, &headers, BODY).is_ok());
+    }
+
+    #[test]
+    fn verify_tampered_body() {
+        let headers = get_headers(SECRET, chrono::Utc::now().timestamp(), BODY);
+        let err = verify_signature(SECRET, &headers, b"payload=%7B%7D").unwrap_err();
+        assert!(err.to_string().contains("does not match"));
+    }
+
+    #[test]
+    fn verify_wrong_secret() {
+        let headers = get_headers("another secret", chrono::Utc::now().timestamp(), BODY);
+        let err = verify_signature(SECRET, &headers, BODY).unwrap_err();
+        assert!(err.to_string().contains("does not match"));
+    }
+
+    #[test]
+    fn verify_stale_timestamp() {
+        let timestamp = chrono::Utc::now().timestamp() - MAX_REQUEST_AGE_SECS - 1;
+        let headers = get_headers(SECRET, timestamp, BODY);
+        let err = verify_signature(SECRET, &headers, BODY).unwrap_err();
+        assert!(err.to_string().contains("too old"));
+    }
+}
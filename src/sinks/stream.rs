@@ -0,0 +1,233 @@
+use crate::gql::Review;
+use crate::settings::{Settings, Stream};
+use crate::sinks::payload::EventPayload;
+use crate::sinks::{NotificationSink, ReviewUpdate};
+use async_trait::async_trait;
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::extract::{Query, State};
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::Router;
+use futures::StreamExt;
+use log::{debug, info, warn};
+use serde::Deserialize;
+use std::collections::VecDeque;
+use std::convert::Infallible;
+use std::sync::{Arc, Mutex};
+use tokio::sync::broadcast;
+
+#[derive(Debug, Clone)]
+struct StreamEvent {
+    // Increasing number, so that SSE clients can tell which events they have seen
+    id: u64,
+    event: &'static str,
+    data: String,
+}
+
+struct History {
+    next_id: u64,
+    recent: VecDeque<StreamEvent>,
+}
+
+#[derive(Deserialize)]
+struct TokenQuery {
+    token: Option<String>,
+}
+
+/// Re-exposes review events on a local HTTP server, so that internal tools don't each need their
+/// own GraphQL subscription with admin credentials.
+///
+/// Events are served as Server-Sent Events at `/events` and as WebSocket text messages at `/ws`,
+/// with the same payload as the webhook sink.
+pub struct StreamSink {
+    settings: Settings,
+    stream: Stream,
+    tx: broadcast::Sender<StreamEvent>,
+    history: Mutex<History>,
+}
+
+impl StreamSink {
+    pub async fn start(settings: Settings) -> anyhow::Result<Arc<Self>> {
+        let stream = settings.stream.clone().ok_or_else(|| {
+            anyhow::anyhow!("Stream sink is enabled, but there is no [stream] section")
+        })?;
+
+        let listener = tokio::net::TcpListener::bind(&stream.listen_addr).await?;
+        info!("Serving review events on {}", stream.listen_addr);
+
+        // Clients that fall this far behind skip events instead of holding up the others
+        let (tx, _) = broadcast::channel(64);
+        let sink = Arc::new(Self {
+            history: Mutex::new(History {
+                next_id: 0,
+                recent: VecDeque::with_capacity(stream.replay),
+            }),
+            settings,
+            stream,
+            tx,
+        });
+
+        let app = Router::new()
+            .route("/events", get(handle_sse))
+            .route("/ws", get(handle_ws))
+            .with_state(sink.clone());
+        tokio::spawn(async move {
+            if let Err(err) = axum::serve(listener, app).await {
+                warn!("Review event server stopped: {}", err);
+            }
+        });
+
+        Ok(sink)
+    }
+
+    fn publish(&self, payload: &EventPayload) -> anyhow::Result<()> {
+        let data = serde_json::to_string(payload)?;
+
+        // Sent while holding the lock, so that connecting clients get every event exactly once
+        let mut history = self.history.lock().unwrap();
+        let event = StreamEvent {
+            id: history.next_id,
+            event: payload.event,
+            data,
+        };
+        history.next_id += 1;
+
+        if self.stream.replay > 0 {
+            if history.recent.len() == self.stream.replay {
+                history.recent.pop_front();
+            }
+            history.recent.push_back(event.clone());
+        }
+
+        // Fails only if no client is connected, which is fine
+        let _ = self.tx.send(event);
+        Ok(())
+    }
+
+    /// Returns the events to replay and a receiver for everything after them
+    fn connect(&self) -> (Vec<StreamEvent>, broadcast::Receiver<StreamEvent>) {
+        let history = self.history.lock().unwrap();
+        (
+            history.recent.iter().cloned().collect(),
+            self.tx.subscribe(),
+        )
+    }
+
+    fn is_authorized(&self, headers: &HeaderMap, query: &TokenQuery) -> bool {
+        // Browsers can't set headers for EventSource or WebSocket, so they use the query instead
+        let token = headers
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .or(query.token.as_deref());
+
+        token.is_some_and(|token| tokens_match(token.as_bytes(), self.stream.token.as_bytes()))
+    }
+}
+
+async fn handle_sse(
+    State(sink): State<Arc<StreamSink>>,
+    headers: HeaderMap,
+    Query(query): Query<TokenQuery>,
+) -> Response {
+    if !sink.is_authorized(&headers, &query) {
+        return StatusCode::UNAUTHORIZED.into_response();
+    }
+    debug!("SSE client connected");
+
+    let (replay, rx) = sink.connect();
+    let events = futures::stream::iter(replay)
+        .chain(live_events(rx))
+        .map(|event| {
+            Ok::<_, Infallible>(
+                Event::default()
+                    .id(event.id.to_string())
+                    .event(event.event)
+                    .data(event.data),
+            )
+        });
+
+    Sse::new(events)
+        .keep_alive(KeepAlive::default())
+        .into_response()
+}
+
+async fn handle_ws(
+    State(sink): State<Arc<StreamSink>>,
+    headers: HeaderMap,
+    Query(query): Query<TokenQuery>,
+    ws: WebSocketUpgrade,
+) -> Response {
+    if !sink.is_authorized(&headers, &query) {
+        return StatusCode::UNAUTHORIZED.into_response();
+    }
+
+    ws.on_upgrade(move |socket| serve_socket(sink, socket))
+}
+
+async fn serve_socket(sink: Arc<StreamSink>, mut socket: WebSocket) {
+    debug!("WebSocket client connected");
+
+    let (replay, rx) = sink.connect();
+    let mut events = Box::pin(futures::stream::iter(replay).chain(live_events(rx)));
+
+    loop {
+        tokio::select! {
+            event = events.next() => {
+                let event = match event {
+                    Some(event) => event,
+                    None => break,
+                };
+                if let Err(err) = socket.send(Message::Text(event.data.into())).await {
+                    debug!("WebSocket client went away: {}", err);
+                    break;
+                }
+            }
+            // Clients are not expected to send anything, this only notices them leaving
+            message = socket.recv() => {
+                match message {
+                    Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                    Some(Ok(_)) => {}
+                }
+            }
+        }
+    }
+
+    debug!("WebSocket client disconnected");
+}
+
+fn live_events(rx: broadcast::Receiver<StreamEvent>) -> impl futures::Stream<Item = StreamEvent> {
+    futures::stream::unfold(rx, |mut rx| async move {
+        loop {
+            match rx.recv().await {
+                Ok(event) => return Some((event, rx)),
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    warn!("Stream client fell behind, skipped {} events", skipped);
+                }
+                Err(broadcast::error::RecvError::Closed) => return None,
+            }
+        }
+    })
+}
+
+/// Compares in constant time, so that the token can't be guessed by timing the responses
+fn tokens_match(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+#[async_trait]
+impl NotificationSink for StreamSink {
+    fn name(&self) -> &str {
+        "stream"
+    }
+
+    async fn deliver_review(&self, review: &Review) -> anyhow::Result<()> {
+        self.publish(&EventPayload::created(&self.settings, review))
+    }
+
+    async fn update_review_state(&self, update: &ReviewUpdate) -> anyhow::Result<()> {
+        self.publish(&EventPayload::updated(&self.settings, update))
+    }
+}
@@ -0,0 +1,690 @@
+use crate::gql::Review;
+use crate::image::ImageClient;
+use crate::settings::{Settings, Telegram};
+use crate::sinks::moderation::{Messages, Moderator, ReviewInfo, ReviewMessage};
+use crate::sinks::render::{escape_html, get_author, get_status, get_title, get_url, shorten};
+use crate::sinks::{NotificationSink, ReviewUpdate};
+use crate::store::ModerationState;
+use async_trait::async_trait;
+use log::{debug, info, warn};
+use reqwest::multipart::{Form, Part};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::sync::Arc;
+use std::time::Duration;
+
+// Captions are limited to 1024 characters, which leaves enough room for the title and status
+const MAX_TEXT_LENGTH: usize = 800;
+
+#[derive(Clone)]
+struct TelegramMessage {
+    message_id: i64,
+    review_id: String,
+    image_id: Option<String>,
+    // The rendered review, without the status line
+    text: String,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+enum Action {
+    Approve,
+    Unapprove,
+    Reject,
+    Delete,
+    Rotate(i32),
+}
+
+#[derive(Deserialize)]
+struct ApiResponse {
+    ok: bool,
+    #[serde(default)]
+    result: Value,
+    description: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Update {
+    update_id: i64,
+    callback_query: Option<CallbackQuery>,
+}
+
+#[derive(Deserialize)]
+struct CallbackQuery {
+    id: String,
+    from: User,
+    message: Option<Message>,
+    data: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct User {
+    id: i64,
+    first_name: String,
+    username: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Message {
+    message_id: i64,
+    chat: Chat,
+    photo: Option<Value>,
+}
+
+#[derive(Deserialize)]
+struct Chat {
+    id: i64,
+}
+
+/// Posts new reviews into a Telegram chat through the Bot API.
+///
+/// Moderation works through an inline keyboard below each message, mirroring the buttons of the
+/// discord bot.
+pub struct TelegramSink {
+    settings: Settings,
+    telegram: Telegram,
+    client: reqwest::Client,
+    moderator: Arc<Moderator>,
+    image_client: ImageClient,
+    // Messages from before a restart are unknown, so their images can't be rotated
+    messages: Messages<TelegramMessage>,
+}
+
+impl TelegramSink {
+    pub async fn start(settings: Settings, moderator: Arc<Moderator>) -> anyhow::Result<Arc<Self>> {
+        let telegram = settings.telegram.clone().ok_or_else(|| {
+            anyhow::anyhow!("Telegram sink is enabled, but there is no [telegram] section")
+        })?;
+
+        let sink = Arc::new(Self {
+            moderator,
+            image_client: ImageClient::new(settings.clone()),
+            settings,
+            telegram,
+            client: reqwest::Client::new(),
+            messages: Messages::default(),
+        });
+
+        let me = sink.call("getMe", &json!({})).await?;
+        info!(
+            "Logged in to telegram as @{}",
+            me["username"].as_str().unwrap_or_default()
+        );
+        if sink.telegram.moderators.is_empty() {
+            warn!(
+                "No telegram moderators are configured, the buttons of reviews won't do anything"
+            );
+        }
+
+        tokio::spawn(sink.clone().listen_for_callbacks());
+
+        Ok(sink)
+    }
+
+    fn method_url(&self, method: &str) -> String {
+        format!(
+            "{}/bot{}/{}",
+            self.telegram.api_url.trim_end_matches('/'),
+            self.telegram.bot_token,
+            method
+        )
+    }
+
+    async fn call(&self, method: &str, body: &Value) -> anyhow::Result<Value> {
+        let resp = self
+            .client
+            .post(self.method_url(method))
+            .json(body)
+            .send()
+            .await?;
+        parse_response(method, resp).await
+    }
+
+    async fn call_multipart(&self, method: &str, form: Form) -> anyhow::Result<Value> {
+        let resp = self
+            .client
+            .post(self.method_url(method))
+            .multipart(form)
+            .send()
+            .await?;
+        parse_response(method, resp).await
+    }
+
+    /// Downloads the image, as telegram must not be handed a url containing our key
+    async fn get_photo(&self, image_id: &str) -> anyhow::Result<Part> {
+        let (data, content_type) = self.image_client.download_image(image_id).await?;
+        Ok(Part::bytes(data)
+            .file_name(image_id.to_string())
+            .mime_str(&content_type)?)
+    }
+
+    async fn send_review(
+        &self,
+        review: &Review,
+        text: &str,
+    ) -> anyhow::Result<(i64, Option<String>)> {
+        let caption = render_caption(text, &ModerationState::Pending, None);
+        let image_id = review.images.first().map(|image| image.id.0.clone());
+        let can_approve = self
+            .moderator
+            .can_approve(&ReviewInfo::new(&self.settings, review));
+
+        if let Some(image_id) = &image_id {
+            let keyboard = get_keyboard(&ModerationState::Pending, &review.id.0, true, can_approve);
+            let result = match self.get_photo(image_id).await {
+                Ok(photo) => {
+                    let form = Form::new()
+                        .text("chat_id", self.telegram.chat_id.to_string())
+                        .text("caption", caption.clone())
+                        .text("parse_mode", "HTML")
+                        .text("reply_markup", keyboard.to_string())
+                        .part("photo", photo);
+                    self.call_multipart("sendPhoto", form).await
+                }
+                Err(err) => Err(err),
+            };
+
+            match result {
+                Ok(message) => return Ok((get_message_id(&message)?, Some(image_id.clone()))),
+                Err(err) => warn!(
+                    "Could not send image {} of review {} to telegram, sending text only: {}",
+                    image_id, review.id, err
+                ),
+            }
+        }
+
+        let message = self
+            .call(
+                "sendMessage",
+                &json!({
+                    "chat_id": self.telegram.chat_id,
+                    "text": caption,
+                    "parse_mode": "HTML",
+                    "reply_markup": get_keyboard(&ModerationState::Pending, &review.id.0, false, can_approve),
+                }),
+            )
+            .await?;
+        Ok((get_message_id(&message)?, None))
+    }
+
+    /// Replaces the caption and keyboard of the review's message according to its current state
+    async fn edit_message(&self, message: &ReviewMessage<TelegramMessage>) -> anyhow::Result<()> {
+        let caption = render_caption(
+            &message.content.text,
+            &message.state,
+            message.who.as_deref(),
+        );
+        let keyboard = get_keyboard(
+            &message.state,
+            &message.content.review_id,
+            message.content.image_id.is_some(),
+            self.moderator.can_approve(&message.info),
+        );
+
+        if message.content.image_id.is_some() {
+            self.call(
+                "editMessageCaption",
+                &json!({
+                    "chat_id": self.telegram.chat_id,
+                    "message_id": message.content.message_id,
+                    "caption": caption,
+                    "parse_mode": "HTML",
+                    "reply_markup": keyboard,
+                }),
+            )
+            .await?;
+        } else {
+            self.call(
+                "editMessageText",
+                &json!({
+                    "chat_id": self.telegram.chat_id,
+                    "message_id": message.content.message_id,
+                    "text": caption,
+                    "parse_mode": "HTML",
+                    "reply_markup": keyboard,
+                }),
+            )
+            .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Uploads the image again after rotating it, as telegram keeps its own copy
+    async fn replace_photo(
+        &self,
+        message: &ReviewMessage<TelegramMessage>,
+        image_id: &str,
+    ) -> anyhow::Result<()> {
+        let caption = render_caption(
+            &message.content.text,
+            &message.state,
+            message.who.as_deref(),
+        );
+        let keyboard = get_keyboard(
+            &message.state,
+            &message.content.review_id,
+            true,
+            self.moderator.can_approve(&message.info),
+        );
+
+        let form = Form::new()
+            .text("chat_id", self.telegram.chat_id.to_string())
+            .text("message_id", message.content.message_id.to_string())
+            .text(
+                "media",
+                json!({
+                    "type": "photo",
+                    "media": "attach://photo",
+                    "caption": caption,
+                    "parse_mode": "HTML",
+                })
+                .to_string(),
+            )
+            .text("reply_markup", keyboard.to_string())
+            .part("photo", self.get_photo(image_id).await?);
+        self.call_multipart("editMessageMedia", form).await?;
+
+        Ok(())
+    }
+
+    async fn listen_for_callbacks(self: Arc<Self>) {
+        let mut offset: Option<i64> = None;
+        loop {
+            let updates = match self.get_updates(offset).await {
+                Ok(updates) => updates,
+                Err(err) => {
+                    warn!(
+                        "Could not get telegram updates, trying again in 10 seconds: {}",
+                        err
+                    );
+                    tokio::time::sleep(Duration::from_secs(10)).await;
+                    continue;
+                }
+            };
+
+            for update in updates {
+                // Confirms the update, so that telegram doesn't send it again
+                offset = Some(update.update_id + 1);
+
+                let query = match update.callback_query {
+                    Some(query) => query,
+                    None => continue,
+                };
+
+                let answer = match self.handle_callback(&query).await {
+                    Ok(answer) => answer,
+                    Err(err) => {
+                        warn!("Could not handle telegram callback: {}", err);
+                        Some("Something went wrong, please try again".to_string())
+                    }
+                };
+
+                // Stops the loading indicator on the button
+                match self
+                    .call(
+                        "answerCallbackQuery",
+                        &json!({ "callback_query_id": query.id, "text": answer }),
+                    )
+                    .await
+                {
+                    Ok(_) => {}
+                    Err(err) => warn!("Could not answer telegram callback: {}", err),
+                }
+            }
+        }
+    }
+
+    async fn get_updates(&self, offset: Option<i64>) -> anyhow::Result<Vec<Update>> {
+        let updates = self
+            .call(
+                "getUpdates",
+                &json!({
+                    "offset": offset,
+                    "timeout": 30,
+                    "allowed_updates": ["callback_query"],
+                }),
+            )
+            .await?;
+        Ok(serde_json::from_value(updates)?)
+    }
+
+    /// Rotates the image of the message, returns a notice if that's not possible
+    async fn rotate(
+        &self,
+        known: Option<&ReviewMessage<TelegramMessage>>,
+        angle: i32,
+    ) -> anyhow::Result<Option<String>> {
+        let (known, image_id) =
+            match known.and_then(|known| Some((known, known.content.image_id.as_ref()?))) {
+                Some(known) => known,
+                None => {
+                    return Ok(Some(
+                        "This image can't be rotated from here, as it was posted before a restart"
+                            .to_string(),
+                    ))
+                }
+            };
+
+        self.image_client.rotate_image(image_id, angle).await?;
+        info!("Successfully rotated image {} by {}", image_id, angle);
+        self.replace_photo(known, image_id).await?;
+        Ok(None)
+    }
+
+    /// Returns a short notice to show to the user, if there is something to tell
+    async fn handle_callback(&self, query: &CallbackQuery) -> anyhow::Result<Option<String>> {
+        let message = match &query.message {
+            Some(message) if message.chat.id == self.telegram.chat_id => message,
+            _ => {
+                debug!("Ignoring telegram callback from another chat");
+                return Ok(None);
+            }
+        };
+
+        let (action, review_id) = match query.data.as_deref().and_then(parse_callback_data) {
+            Some(parsed) => parsed,
+            None => {
+                warn!(
+                    "Received telegram callback with invalid data: {:?}",
+                    query.data
+                );
+                return Ok(None);
+            }
+        };
+
+        let who = query
+            .from
+            .username
+            .as_ref()
+            .map(|username| format!("@{}", username))
+            .unwrap_or(query.from.first_name.clone());
+        info!("{} pressed {:?} on review {}", who, action, review_id);
+
+        if !self.telegram.moderators.contains(&query.from.id) {
+            info!("Refused {:?} of {}, who is no moderator", action, who);
+            return Ok(Some("You are not allowed to moderate reviews".to_string()));
+        }
+
+        let known = self.messages.get(review_id);
+        if known.as_ref().map(|message| message.state) == Some(ModerationState::Deleted) {
+            return Ok(Some("This review has already been deleted".to_string()));
+        }
+
+        let info = known
+            .as_ref()
+            .map(|message| message.info.clone())
+            .unwrap_or_default();
+        if action == Action::Approve && !self.moderator.can_approve(&info) {
+            return Ok(Some(
+                "The review may need several approvals, please approve on discord".to_string(),
+            ));
+        }
+
+        let state = match action {
+            Action::Rotate(angle) => return self.rotate(known.as_ref(), angle).await,
+            Action::Approve => ModerationState::Approved,
+            Action::Unapprove => ModerationState::Unapproved,
+            Action::Reject => ModerationState::Rejected,
+            Action::Delete => ModerationState::Deleted,
+        };
+
+        self.moderator
+            .moderate(self.name(), &self.messages, review_id, state, &who)
+            .await?;
+
+        match self.messages.get(review_id) {
+            Some(known) => self.edit_message(&known).await?,
+            None => {
+                // Without the rendered review, we can only replace the keyboard
+                self.call(
+                    "editMessageReplyMarkup",
+                    &json!({
+                        "chat_id": self.telegram.chat_id,
+                        "message_id": message.message_id,
+                        "reply_markup": get_keyboard(&state, review_id, message.photo.is_some(), self.moderator.can_approve(&info)),
+                    }),
+                )
+                .await?;
+                return Ok(Some(get_status(&state, Some(&who))));
+            }
+        }
+
+        Ok(None)
+    }
+}
+
+#[async_trait]
+impl NotificationSink for TelegramSink {
+    fn name(&self) -> &str {
+        "telegram"
+    }
+
+    async fn deliver_review(&self, review: &Review) -> anyhow::Result<()> {
+        if self.messages.contains(&review.id.0) {
+            info!(
+                "Review {} already has a telegram message, skipping it",
+                review.id
+            );
+            return Ok(());
+        }
+
+        let text = render_review(&self.settings, review);
+        let (message_id, image_id) = self.send_review(review, &text).await?;
+
+        self.messages.insert(
+            &review.id.0,
+            ReviewInfo::new(&self.settings, review),
+            TelegramMessage {
+                message_id,
+                review_id: review.id.0.clone(),
+                image_id,
+                text,
+            },
+        );
+
+        Ok(())
+    }
+
+    async fn update_review_state(&self, update: &ReviewUpdate) -> anyhow::Result<()> {
+        let message = match update {
+            ReviewUpdate::Accepted(review) => self
+                .messages
+                .set_external_state(&review.id.0, ModerationState::Approved),
+            ReviewUpdate::Unapproved(review_id) => self
+                .messages
+                .set_external_state(&review_id.0, ModerationState::Unapproved),
+            ReviewUpdate::Rejected(review_id) => self
+                .messages
+                .set_external_state(&review_id.0, ModerationState::Rejected),
+            ReviewUpdate::Deleted(review_id) => self
+                .messages
+                .set_external_state(&review_id.0, ModerationState::Deleted),
+            ReviewUpdate::Edited(review) => {
+                let text = render_review(&self.settings, review);
+                self.messages
+                    .edit(&review.id.0, |message| message.text = text)
+            }
+        };
+
+        match message {
+            Some(message) => self.edit_message(&message).await,
+            None => Ok(()),
+        }
+    }
+}
+
+async fn parse_response(method: &str, resp: reqwest::Response) -> anyhow::Result<Value> {
+    // Errors are reported in the body as well, which is more helpful than the status code
+    let resp: ApiResponse = resp.json().await?;
+    if !resp.ok {
+        anyhow::bail!(
+            "Telegram {} failed: {}",
+            method,
+            resp.description.unwrap_or_default()
+        );
+    }
+    Ok(resp.result)
+}
+
+fn get_message_id(message: &Value) -> anyhow::Result<i64> {
+    message["message_id"]
+        .as_i64()
+        .ok_or_else(|| anyhow::anyhow!("Telegram did not return a message id"))
+}
+
+fn parse_callback_data(data: &str) -> Option<(Action, &str)> {
+    let split = data.split('_').collect::<Vec<_>>();
+    let action = match (split.first()?, split.get(2)) {
+        (&"approve", None) => Action::Approve,
+        (&"unapprove", None) => Action::Unapprove,
+        (&"reject", None) => Action::Reject,
+        (&"delete", None) => Action::Delete,
+        (&"rotate", Some(angle)) => Action::Rotate(angle.parse().ok()?),
+        _ => return None,
+    };
+    Some((action, split.get(1)?))
+}
+
+/// Inline keyboard for a message in the given state, following `get_action_row` of the discord bot
+///
+/// As telegram has no disabled buttons, finished actions are only shown in the status line.
+fn get_keyboard(
+    state: &ModerationState,
+    review_id: &str,
+    has_image: bool,
+    can_approve: bool,
+) -> Value {
+    let button = |text: &str, action: &str| {
+        // Same layout as the discord custom ids, e.g. `rotate_<review id>_90`
+        let callback_data = match action.split_once('_') {
+            Some((action, angle)) => format!("{}_{}_{}", action, review_id, angle),
+            None => format!("{}_{}", action, review_id),
+        };
+        json!({ "text": text, "callback_data": callback_data })
+    };
+
+    let mut moderation_row = match state {
+        ModerationState::Pending | ModerationState::Unapproved => vec![
+            button("✅ Approve", "approve"),
+            button("🗑 Reject", "reject"),
+        ],
+        ModerationState::Approved => vec![button("🗑 Unapprove", "unapprove")],
+        ModerationState::Rejected => vec![
+            button("✅ Approve", "approve"),
+            button("🗑 Delete", "delete"),
+        ],
+        ModerationState::Deleted => vec![],
+    };
+    if !can_approve {
+        moderation_row.retain(|button| button["text"] != "✅ Approve");
+    }
+
+    let mut rows = vec![];
+    if !moderation_row.is_empty() {
+        rows.push(moderation_row);
+    }
+    if has_image && *state != ModerationState::Deleted {
+        rows.push(vec![
+            button("↪", "rotate_270"),
+            button("↕", "rotate_180"),
+            button("↩", "rotate_90"),
+        ]);
+    }
+
+    json!({ "inline_keyboard": rows })
+}
+
+/// HTML version of a review, laid out like the discord embed
+fn render_review(settings: &Settings, review: &Review) -> String {
+    let mut text = format!(
+        "<b><a href=\"{}\">{}</a></b>\nby {}",
+        escape_html(&get_url(settings, review)),
+        escape_html(&get_title(review)),
+        escape_html(&get_author(review))
+    );
+
+    if let Some(review_text) = review.text.as_ref().filter(|text| !text.is_empty()) {
+        text.push_str(&format!(
+            "\n\n{}",
+            escape_html(&shorten(review_text, MAX_TEXT_LENGTH))
+        ));
+    }
+
+    text
+}
+
+fn render_caption(text: &str, state: &ModerationState, who: Option<&str>) -> String {
+    format!(
+        "{}\n\n<i>{}</i>",
+        text,
+        escape_html(&get_status(state, who))
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const REVIEW: &str = "4f9c1d2e-8a3b-4c5d-9e6f-7a8b9c0d1e2f";
+
+    #[test]
+    fn parse_moderation() {
+        for (kind, action) in [
+            ("approve", Action::Approve),
+            ("unapprove", Action::Unapprove),
+            ("reject", Action::Reject),
+            ("delete", Action::Delete),
+        ] {
+            assert_eq!(
+                parse_callback_data(&format!("{}_{}", kind, REVIEW)),
+                Some((action, REVIEW))
+            );
+        }
+    }
+
+    #[test]
+    fn parse_rotate() {
+        assert_eq!(
+            parse_callback_data(&format!("rotate_{}_90", REVIEW)),
+            Some((Action::Rotate(90), REVIEW))
+        );
+        assert_eq!(
+            parse_callback_data(&format!("rotate_{}_270", REVIEW)),
+            Some((Action::Rotate(270), REVIEW))
+        );
+    }
+
+    #[test]
+    fn parse_invalid() {
+        assert_eq!(parse_callback_data(""), None);
+        assert_eq!(parse_callback_data("approve"), None);
+        assert_eq!(parse_callback_data(&format!("approve_{}_90", REVIEW)), None);
+        assert_eq!(parse_callback_data(&format!("rotate_{}", REVIEW)), None);
+        assert_eq!(
+            parse_callback_data(&format!("rotate_{}_left", REVIEW)),
+            None
+        );
+        assert_eq!(parse_callback_data(&format!("publish_{}", REVIEW)), None);
+    }
+
+    #[test]
+    fn parse_keyboard_buttons() {
+        for state in [
+            ModerationState::Pending,
+            ModerationState::Approved,
+            ModerationState::Unapproved,
+            ModerationState::Rejected,
+        ] {
+            let keyboard = get_keyboard(&state, REVIEW, true, true);
+            for button in keyboard["inline_keyboard"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .flat_map(|row| row.as_array().unwrap())
+            {
+                let data = button["callback_data"].as_str().unwrap();
+                assert_eq!(parse_callback_data(data).map(|(_, id)| id), Some(REVIEW));
+            }
+        }
+    }
+}
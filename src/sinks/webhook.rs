@@ -0,0 +1,169 @@
+use crate::gql::Review;
+use crate::settings::{Settings, Webhook, WebhookEndpoint};
+use crate::sinks::payload::EventPayload;
+use crate::sinks::{NotificationSink, ReviewUpdate};
+use async_trait::async_trait;
+use futures::future::join_all;
+use hmac::{Hmac, Mac};
+use log::{debug, info, warn};
+use sha2::Sha256;
+use std::time::Duration;
+
+// Upper bound for the delay between retries, however often they failed
+const MAX_BACKOFF: Duration = Duration::from_secs(300);
+
+/// POSTs every review event as JSON to the configured endpoints.
+///
+/// The body is signed with HMAC-SHA256 using the secret of the endpoint, the hex encoded signature
+/// is sent in the `X-Signature` header.
+pub struct WebhookSink {
+    settings: Settings,
+    webhook: Webhook,
+    client: reqwest::Client,
+}
+
+impl WebhookSink {
+    pub fn new(settings: Settings) -> anyhow::Result<Self> {
+        let webhook = settings.webhook.clone().ok_or_else(|| {
+            anyhow::anyhow!("Webhook sink is enabled, but there is no [webhook] section")
+        })?;
+
+        Ok(Self {
+            settings,
+            webhook,
+            client: reqwest::Client::new(),
+        })
+    }
+
+    async fn send(&self, payload: &EventPayload) -> anyhow::Result<()> {
+        let body = serde_json::to_vec(payload)?;
+
+        // A slow endpoint retrying shouldn't hold up the others
+        let results = join_all(
+            self.webhook
+                .endpoints
+                .iter()
+                .map(|endpoint| self.send_with_retries(endpoint, &body)),
+        )
+        .await;
+
+        let mut failed = 0;
+        for (endpoint, result) in self.webhook.endpoints.iter().zip(results) {
+            if let Err(err) = result {
+                warn!(
+                    "Giving up on delivering {} event for review {} to {}: {}",
+                    payload.event, payload.review_id, endpoint.url, err
+                );
+                failed += 1;
+            }
+        }
+
+        if failed > 0 {
+            return Err(anyhow::anyhow!(
+                "Delivery to {} of {} endpoints failed",
+                failed,
+                self.webhook.endpoints.len()
+            ));
+        }
+
+        Ok(())
+    }
+
+    async fn send_with_retries(
+        &self,
+        endpoint: &WebhookEndpoint,
+        body: &[u8],
+    ) -> anyhow::Result<()> {
+        let signature = sign(&endpoint.secret, body)?;
+
+        let mut backoff = Duration::from_secs(self.webhook.initial_backoff_secs);
+        let mut attempt = 0;
+        loop {
+            attempt += 1;
+
+            let result = self
+                .client
+                .post(&endpoint.url)
+                .header("Content-Type", "application/json")
+                .header("X-Signature", &signature)
+                .body(body.to_vec())
+                .send()
+                .await;
+
+            let retryable = match result {
+                Ok(resp) if resp.status().is_success() => {
+                    debug!("Delivered webhook to {}", endpoint.url);
+                    return Ok(());
+                }
+                Ok(resp) => {
+                    let status = resp.status();
+                    // Anything but server errors and rate limiting won't get better by retrying
+                    if !status.is_server_error() && status != reqwest::StatusCode::TOO_MANY_REQUESTS
+                    {
+                        return Err(anyhow::anyhow!("Endpoint responded with {}", status));
+                    }
+                    anyhow::anyhow!("Endpoint responded with {}", status)
+                }
+                Err(err) => err.into(),
+            };
+
+            if attempt > self.webhook.max_retries {
+                return Err(retryable);
+            }
+
+            info!(
+                "Delivering webhook to {} failed (attempt {}), retrying in {} seconds: {}",
+                endpoint.url,
+                attempt,
+                backoff.as_secs(),
+                retryable
+            );
+            tokio::time::sleep(backoff).await;
+            backoff = backoff.saturating_mul(2).min(MAX_BACKOFF);
+        }
+    }
+}
+
+fn sign(secret: &str, body: &[u8]) -> anyhow::Result<String> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes())?;
+    mac.update(body);
+    Ok(hex::encode(mac.finalize().into_bytes()))
+}
+
+#[async_trait]
+impl NotificationSink for WebhookSink {
+    fn name(&self) -> &str {
+        "webhook"
+    }
+
+    async fn deliver_review(&self, review: &Review) -> anyhow::Result<()> {
+        self.send(&EventPayload::created(&self.settings, review))
+            .await
+    }
+
+    async fn update_review_state(&self, update: &ReviewUpdate) -> anyhow::Result<()> {
+        self.send(&EventPayload::updated(&self.settings, update))
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sign_matches_hmac_sha256() {
+        // Test case 2 of RFC 4231
+        assert_eq!(
+            sign("Jefe", b"what do ya want for nothing?").unwrap(),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    #[test]
+    fn sign_depends_on_secret() {
+        let body = br#"{"event":"created"}"#;
+        assert_ne!(sign("a", body).unwrap(), sign("b", body).unwrap());
+        assert_eq!(sign("a", body).unwrap(), sign("a", body).unwrap());
+    }
+}
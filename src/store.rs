@@ -0,0 +1,287 @@
+use crate::settings::Settings;
+use chrono::{DateTime, Utc};
+use log::{debug, info};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ModerationState {
+    Pending,
+    Approved,
+    Unapproved,
+    Rejected,
+    Deleted,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReviewRecord {
+    // Missing for records from before it was stored
+    #[serde(default)]
+    pub guild_id: Option<u64>,
+    // Name of the mensa, missing for records from before it was stored
+    #[serde(default)]
+    pub location: Option<String>,
+    pub channel_id: u64,
+    pub message_id: u64,
+    pub state: ModerationState,
+    // Discord users who voted to approve the review, while it still needs more votes
+    #[serde(default)]
+    pub approvals: Vec<u64>,
+    // Moderator currently handling the review, others can't moderate it in the meantime
+    #[serde(default)]
+    pub claim: Option<Claim>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Claim {
+    pub user_id: u64,
+    pub user_name: String,
+    pub until: DateTime<Utc>,
+}
+
+impl Claim {
+    pub fn is_active(&self) -> bool {
+        self.until > Utc::now()
+    }
+}
+
+pub enum ClaimOutcome {
+    Claimed,
+    Released,
+    // Someone else holds an active claim
+    Taken(Claim),
+    UnknownReview,
+}
+
+pub enum Vote {
+    // Number of approvals including this one, while more are needed
+    Counted(usize),
+    // The vote completed the quorum and the review is marked as approved, holds the previous state
+    Reached(ModerationState),
+    Repeated,
+    // Someone else completed the quorum already
+    Closed,
+    UnknownReview,
+}
+
+/// Remembers which discord message belongs to which review, so that we don't post a review twice.
+///
+/// Everything is kept in memory and written to a JSON file on every change. The number of
+/// reviews is small enough that this is a lot simpler than a proper database, as long as records
+/// are removed once the review is approved or deleted.
+pub struct ReviewStore {
+    path: PathBuf,
+    records: Mutex<HashMap<String, ReviewRecord>>,
+    // Bumped on every change, so that a slow write can't overwrite a newer one
+    version: AtomicU64,
+    // Version that was last written to the file
+    written: Arc<Mutex<u64>>,
+}
+
+impl ReviewStore {
+    pub fn open(settings: &Settings) -> anyhow::Result<Self> {
+        let path = PathBuf::from(&settings.store.path);
+
+        let records = if path.exists() {
+            let content = std::fs::read_to_string(&path)?;
+            serde_json::from_str(&content)?
+        } else {
+            HashMap::new()
+        };
+
+        info!(
+            "Loaded {} review records from {}",
+            records.len(),
+            path.display()
+        );
+
+        Ok(Self {
+            path,
+            records: Mutex::new(records),
+            version: AtomicU64::new(0),
+            written: Arc::new(Mutex::new(0)),
+        })
+    }
+
+    pub fn get(&self, review_id: &str) -> Option<ReviewRecord> {
+        self.records.lock().unwrap().get(review_id).cloned()
+    }
+
+    pub async fn insert(&self, review_id: &str, record: ReviewRecord) -> anyhow::Result<()> {
+        let snapshot = {
+            let mut records = self.records.lock().unwrap();
+            records.insert(review_id.to_string(), record);
+            self.snapshot(&records)?
+        };
+        self.persist(snapshot).await
+    }
+
+    /// Forgets the review once nothing can happen to it anymore
+    pub async fn remove(&self, review_id: &str) -> anyhow::Result<()> {
+        let snapshot = {
+            let mut records = self.records.lock().unwrap();
+            if records.remove(review_id).is_none() {
+                return Ok(());
+            }
+            self.snapshot(&records)?
+        };
+        self.persist(snapshot).await
+    }
+
+    /// Returns the previous state, if the review is known
+    pub async fn set_state(
+        &self,
+        review_id: &str,
+        state: ModerationState,
+    ) -> anyhow::Result<Option<ModerationState>> {
+        let (previous, snapshot) = {
+            let mut records = self.records.lock().unwrap();
+            let previous = match records.get_mut(review_id) {
+                Some(record) => {
+                    // Approving again after the review was taken back needs a new quorum, while
+                    // votes survive an approval that failed and was reverted to pending
+                    if matches!(
+                        state,
+                        ModerationState::Unapproved
+                            | ModerationState::Rejected
+                            | ModerationState::Deleted
+                    ) {
+                        record.approvals.clear();
+                    }
+                    // Whoever claimed the review is done with it
+                    record.claim = None;
+                    std::mem::replace(&mut record.state, state)
+                }
+                None => {
+                    // Happens for messages that were sent before the store existed
+                    debug!("No record for review {}, not updating state", review_id);
+                    return Ok(None);
+                }
+            };
+            (previous, self.snapshot(&records)?)
+        };
+        self.persist(snapshot).await?;
+        Ok(Some(previous))
+    }
+
+    /// Claims the review for the user, or releases it if the user already claimed it
+    pub async fn toggle_claim(
+        &self,
+        review_id: &str,
+        claim: Claim,
+    ) -> anyhow::Result<ClaimOutcome> {
+        let (outcome, snapshot) = {
+            let mut records = self.records.lock().unwrap();
+            let record = match records.get_mut(review_id) {
+                Some(record) => record,
+                None => return Ok(ClaimOutcome::UnknownReview),
+            };
+
+            let outcome = match record.claim.take() {
+                Some(existing) if existing.user_id == claim.user_id && existing.is_active() => {
+                    ClaimOutcome::Released
+                }
+                Some(existing) if existing.is_active() => {
+                    record.claim = Some(existing.clone());
+                    return Ok(ClaimOutcome::Taken(existing));
+                }
+                _ => {
+                    record.claim = Some(claim);
+                    ClaimOutcome::Claimed
+                }
+            };
+            (outcome, self.snapshot(&records)?)
+        };
+        self.persist(snapshot).await?;
+        Ok(outcome)
+    }
+
+    /// Counts the approval and marks the review as approved once `required` approvals are in.
+    ///
+    /// Both happen under the same lock, so only one vote can complete the quorum.
+    pub async fn add_approval(
+        &self,
+        review_id: &str,
+        user_id: u64,
+        required: usize,
+    ) -> anyhow::Result<Vote> {
+        let (vote, snapshot) = {
+            let mut records = self.records.lock().unwrap();
+            let record = match records.get_mut(review_id) {
+                Some(record) => record,
+                None => return Ok(Vote::UnknownReview),
+            };
+            if record.state == ModerationState::Approved {
+                return Ok(Vote::Closed);
+            }
+            if record.approvals.contains(&user_id) {
+                return Ok(Vote::Repeated);
+            }
+
+            record.approvals.push(user_id);
+            let votes = record.approvals.len();
+            let vote = if votes >= required {
+                record.claim = None;
+                Vote::Reached(std::mem::replace(
+                    &mut record.state,
+                    ModerationState::Approved,
+                ))
+            } else {
+                Vote::Counted(votes)
+            };
+            (vote, self.snapshot(&records)?)
+        };
+        // A vote that isn't stored would be lost on restart, so it doesn't count
+        if let Err(err) = self.persist(snapshot).await {
+            if let Some(record) = self.records.lock().unwrap().get_mut(review_id) {
+                record.approvals.retain(|approval| *approval != user_id);
+                if let Vote::Reached(previous) = vote {
+                    record.state = previous;
+                }
+            }
+            return Err(err);
+        }
+        Ok(vote)
+    }
+
+    pub async fn remove_approval(&self, review_id: &str, user_id: u64) -> anyhow::Result<()> {
+        let snapshot = {
+            let mut records = self.records.lock().unwrap();
+            if let Some(record) = records.get_mut(review_id) {
+                record.approvals.retain(|approval| *approval != user_id);
+            }
+            self.snapshot(&records)?
+        };
+        self.persist(snapshot).await
+    }
+
+    /// Serializes the records while the lock is still held, the file is written afterwards
+    fn snapshot(&self, records: &HashMap<String, ReviewRecord>) -> anyhow::Result<(u64, String)> {
+        let version = self.version.fetch_add(1, Ordering::SeqCst) + 1;
+        Ok((version, serde_json::to_string_pretty(records)?))
+    }
+
+    /// Writes on a blocking thread, so that the async handlers don't wait for the disk
+    async fn persist(&self, (version, content): (u64, String)) -> anyhow::Result<()> {
+        let path = self.path.clone();
+        let written = self.written.clone();
+        tokio::task::spawn_blocking(move || {
+            let mut written = written.lock().unwrap();
+            // A later change was written already, which includes this one
+            if *written > version {
+                return Ok(());
+            }
+            // Write to a temporary file first, so a crash mid-write can't corrupt the store
+            let tmp_path = path.with_extension("tmp");
+            std::fs::write(&tmp_path, content)?;
+            std::fs::rename(&tmp_path, &path)?;
+            *written = version;
+            Ok(())
+        })
+        .await?
+    }
+}